@@ -1,117 +1,177 @@
-use std::{ time::Instant, fs };
-use lib::get_part;
+use std::{ cmp::Reverse, collections::BinaryHeap, fmt::Display, time::Instant, fs };
+use lib::{ get_part, graph::Graph, has_flag };
 
-struct SafetyManual<'a> {
-    rules: Vec<(&'a str, &'a str)>,
-    pages: Vec<Vec<&'a str>>,
+struct SafetyManual {
+    /** `a -> b` means a has to come before b */
+    rules: Graph,
+    updates: Vec<Vec<u32>>,
 }
 
-impl<'a> SafetyManual<'a> {
-    // first where; I don't understand it
-    fn new<'b>(data: &'b str) -> Self where 'b: 'a {
+/** pages that each have to come before the next, and the last before the first */
+#[derive(Debug, PartialEq)]
+struct Cycle(Vec<String>);
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pages can't be ordered: {} -> {}", self.0.join(" -> "), self.0[0])
+    }
+}
+
+impl SafetyManual {
+    fn new(data: &str) -> Self {
         let (a, b) = data.split_once("\n\n").expect("What, no space!?");
+        let mut rules = Graph::directed();
 
-        let rules: Vec<(&str, &str)> = a
-            .lines()
-            .map(|l| l.split_once("|").expect("What, no pipe!?"))
-            .collect();
+        for l in a.lines() {
+            let (first, second) = l.split_once("|").expect("What, no pipe!?");
+
+            rules.connect(first, second);
+        }
 
-        let pages: Vec<Vec<&str>> = b
+        let updates = b
             .lines()
-            .map(|l| l.split(",").collect())
+            .map(|l| {
+                l.split(",")
+                    .map(|page| rules.intern(page))
+                    .collect()
+            })
             .collect();
 
-        Self { rules, pages }
+        Self { rules, updates }
     }
 
-    fn get_failed(&self) -> Vec<bool> {
-        let len = self.pages.len();
-        let mut failed = vec![false; len];
+    /**
+     * Kahn's algorithm on just the rules between pages in `update`;
+     * when there's a choice, the page that was earliest in the update goes first,
+     * so a correct update comes back unchanged
+     */
+    fn order(&self, update: &[u32]) -> Result<Vec<u32>, Cycle> {
+        // induced id i is update[i]
+        let rules = self.rules.induced(update);
+        let mut indegree = vec![0; update.len()];
 
-        for (first, second) in self.rules.iter() {
-            for p in 0..len {
-                if failed[p] {
-                    continue;
-                }
-                if let Some(s) = self.pages[p].iter().position(|x| x == second) {
-                    // found second, let's see if first comes after
-                    if self.pages[p][s..].contains(first) {
-                        // println!("Found {} after {}; failed: {:?}", first, second, manual.pages[p]);
-                        failed[p] = true;
-                    }
+        for page in rules.nodes() {
+            for &next in rules.neighbours(page) {
+                indegree[next as usize] += 1;
+            }
+        }
+
+        let mut heap: BinaryHeap<_> = rules
+            .nodes()
+            .filter(|&p| indegree[p as usize] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(update.len());
+
+        while let Some(Reverse(page)) = heap.pop() {
+            order.push(update[page as usize]);
+
+            for &next in rules.neighbours(page) {
+                indegree[next as usize] -= 1;
+
+                if indegree[next as usize] == 0 {
+                    heap.push(Reverse(next));
                 }
             }
         }
 
-        failed
+        if order.len() < update.len() {
+            return Err(Cycle(find_cycle(&rules, &indegree)));
+        }
+
+        Ok(order)
+    }
+
+    fn is_ordered(&self, update: &[u32]) -> bool {
+        self.order(update).is_ok_and(|order| order == update)
+    }
+
+    fn middle(&self, update: &[u32]) -> usize {
+        self.rules
+            .name(update[update.len() / 2])
+            .parse()
+            .expect("Thought it was a number :(")
+    }
+
+    fn names(&self, update: &[u32]) -> String {
+        update
+            .iter()
+            .map(|&page| self.rules.name(page))
+            .collect::<Vec<_>>()
+            .join(",")
     }
 }
 
-fn part_one(manual: &SafetyManual) -> usize {
-    let failed = manual.get_failed();
+/**
+ * every page left with an indegree is on (or after) a cycle, so it has a
+ * predecessor that's also left; walk back until we repeat
+ */
+fn find_cycle(rules: &Graph, indegree: &[usize]) -> Vec<String> {
+    let mut before = vec![vec![]; rules.len()];
 
-    manual.pages
-        .iter()
-        .enumerate()
-        .filter_map(|p| {
-            if failed[p.0] {
-                return None;
-            }
-            let mid = p.1[p.1.len() / 2];
+    for page in rules.nodes() {
+        for &next in rules.neighbours(page) {
+            before[next as usize].push(page);
+        }
+    }
 
-            // println!("Mid: {}", mid);
+    let left = |p: u32| indegree[p as usize] > 0;
+    let mut page = rules
+        .nodes()
+        .find(|&p| left(p))
+        .unwrap();
+    let mut path = vec![];
 
-            Some(mid.parse::<usize>().expect("Thought it was a number :("))
-        })
-        .sum()
-}
+    while !path.contains(&page) {
+        path.push(page);
+        page = *before[page as usize]
+            .iter()
+            .find(|&&p| left(p))
+            .unwrap();
+    }
+
+    let start = path
+        .iter()
+        .position(|&p| p == page)
+        .unwrap();
 
-fn part_two(manual: &SafetyManual) -> usize {
-    let failed = manual.get_failed();
+    // walked backwards; start from whichever was earliest in the update
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
 
-    let failed_pages: Vec<_> = manual.pages
+    let first = cycle
         .iter()
         .enumerate()
-        .filter_map(|p| {
-            if failed[p.0] {
-                return Some(p.1.clone());
-            }
-            None
-        })
-        .collect();
+        .min_by_key(|(_, &p)| p)
+        .unwrap().0;
+    cycle.rotate_left(first);
+
+    cycle
+        .iter()
+        .map(|&p| rules.name(p).to_string())
+        .collect()
+}
+
+fn part_one(manual: &SafetyManual) -> usize {
+    manual.updates
+        .iter()
+        .filter(|update| manual.is_ordered(update))
+        .map(|update| manual.middle(update))
+        .sum()
+}
 
+fn part_two(manual: &SafetyManual) -> Result<usize, Cycle> {
     let mut sum = 0;
-    // first for mut?
-    for mut page in failed_pages {
-        loop {
-            let mut swapped = false;
-
-            'outer: for &(first, second) in manual.rules.iter() {
-                for i in 0..page.len() {
-                    if page[i] == first {
-                        continue 'outer;
-                    }
-                    if page[i] == second {
-                        // look for first and swap
-                        for j in i..page.len() {
-                            if page[j] == first {
-                                page.swap(i, j);
-                                swapped = true;
-                                continue 'outer;
-                            }
-                        }
-                    }
-                }
-            }
 
-            if !swapped {
-                sum += page[page.len() / 2].parse::<usize>().expect("it to be a number");
-                break;
-            }
+    for update in manual.updates.iter() {
+        let order = manual.order(update)?;
+
+        if order != *update {
+            sum += manual.middle(&order);
         }
     }
 
-    sum
+    Ok(sum)
 }
 
 fn main() {
@@ -129,11 +189,23 @@ fn main() {
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&data);
-        println!("Part two: {:?} {:?}", ans, now.elapsed());
+        match part_two(&data) {
+            Ok(ans) => println!("Part two: {:?} {:?}", ans, now.elapsed()),
+            Err(e) => println!("Part two: {e}"),
+        }
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    if has_flag("fixed") {
+        for update in data.updates.iter() {
+            match data.order(update) {
+                Ok(order) if order == *update => {}
+                Ok(order) => println!("{} => {}", data.names(update), data.names(&order)),
+                Err(e) => println!("{}: {e}", data.names(update)),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -155,6 +227,36 @@ mod tests {
         let data = SafetyManual::new(EXAMPLE);
         let ans = part_two(&data);
 
-        assert_eq!(ans, 123);
+        assert_eq!(ans, Ok(123));
+    }
+
+    #[test]
+    fn test_order() {
+        let data = SafetyManual::new(EXAMPLE);
+        let fixed = data.updates
+            .iter()
+            .map(|update| data.names(&data.order(update).unwrap()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(fixed, [
+            "75,47,61,53,29",
+            "97,61,53,29,13",
+            "75,29,13",
+            "97,75,47,61,53",
+            "61,29,13",
+            "97,75,47,29,13",
+        ]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let data = SafetyManual::new("1|2\n2|3\n3|1\n4|1\n\n4,1,2\n1,2,3,4");
+
+        assert_eq!(part_one(&data), 1);
+        assert_eq!(part_two(&data), Err(Cycle(vec!["1".into(), "2".into(), "3".into()])));
+        assert_eq!(
+            part_two(&data).unwrap_err().to_string(),
+            "pages can't be ordered: 1 -> 2 -> 3 -> 1"
+        );
     }
 }
@@ -1,190 +1,290 @@
-use std::{ collections::HashSet, fs, time::Instant };
-use lib::{ get_part, DIRS };
+use std::{ fs, thread, time::Instant };
+use lib::{ flag_value, get_part, has_flag, Grid, DIRS };
 
-struct Grid {
-    cells: Vec<Vec<char>>,
-    height: isize,
-    width: isize,
+/** where a jump goes when the guard walks off the map */
+const EXIT: u32 = u32::MAX;
+
+struct Patrol {
+    grid: Grid,
     start: (isize, isize),
+    /** for each cell and direction: where the guard stops before the next wall, or EXIT */
+    jumps: Vec<[u32; 4]>,
 }
 
-impl Grid {
+impl Patrol {
     fn new(data: &str) -> Self {
-        let cells: Vec<_> = data
-            .lines()
-            .map(|l| { l.chars().collect::<Vec<_>>() })
-            .collect();
-
-        let height = cells.len() as isize;
-        let width = cells[0].len() as isize;
+        let grid = Grid::new_with_chars(data);
 
-        let start = cells
+        let start = grid
             .iter()
-            .enumerate()
-            .find_map(|(r, row)| {
-                for (c, &cell) in row.iter().enumerate() {
-                    if cell == '^' {
-                        return Some((r as isize, c as isize));
-                    }
-                }
-                None
-            })
+            .find_map(|(r, c, &cell)| (cell == '^').then_some((r as isize, c as isize)))
             .expect("find_map to do its job");
 
-        Self { cells, height, width, start }
+        let jumps = vec![[EXIT; 4]; (grid.width * grid.height) as usize];
+        let mut patrol = Self { grid, start, jumps };
+
+        // each cell's jump is the jump of the cell in front of it, so
+        // sweep from the far side of each direction
+        for (d, dir) in DIRS.iter().enumerate() {
+            let rows = (0..patrol.grid.height).collect::<Vec<_>>();
+            let cols = (0..patrol.grid.width).collect::<Vec<_>>();
+            let rows = if dir.0 > 0 { rows.into_iter().rev().collect() } else { rows };
+            let cols = if dir.1 > 0 { cols.into_iter().rev().collect() } else { cols };
+
+            for &r in rows.iter() {
+                for &c in cols.iter() {
+                    let ahead = (r + dir.0, c + dir.1);
+                    let cell = patrol.index((r, c));
+
+                    patrol.jumps[cell][d] = match patrol.grid.get(ahead) {
+                        None => EXIT,
+                        Some('#') => cell as u32,
+                        Some(_) => patrol.jumps[patrol.index(ahead)][d],
+                    };
+                }
+            }
+        }
+
+        patrol
     }
 
-    fn get(&self, pos: (isize, isize)) -> Option<char> {
-        if pos.0 == -1 || pos.1 == -1 || pos.0 >= self.height || pos.1 >= self.width {
-            return None;
-        }
+    fn index(&self, pos: (isize, isize)) -> usize {
+        (pos.0 * self.grid.width + pos.1) as usize
+    }
 
-        Some(self.cells[pos.0 as usize][pos.1 as usize])
+    fn pos(&self, cell: u32) -> (isize, isize) {
+        ((cell as isize) / self.grid.width, (cell as isize) % self.grid.width)
     }
 
-    fn try_obstacle(&self, obstacle: (isize, isize), start: (isize, isize), dir: usize) -> bool {
-        let mut cur = start;
-        let mut visited = vec![vec![0; self.width as usize]; self.height as usize];
+    /**
+     * the jump table, patched for one extra `obstacle`: if it's in the way,
+     * stop just in front of it instead
+     */
+    fn jump(&self, cell: u32, d: usize, obstacle: (isize, isize)) -> u32 {
+        let stop = self.jumps[cell as usize][d];
+        let (r, c) = self.pos(cell);
+        let dir = DIRS[d];
+
+        // how many steps ahead the obstacle is, if it's in line at all
+        let ahead = if dir.0 == 0 {
+            if obstacle.0 != r { 0 } else { (obstacle.1 - c) * dir.1 }
+        } else if obstacle.1 != c {
+            0
+        } else {
+            (obstacle.0 - r) * dir.0
+        };
+
+        if ahead <= 0 {
+            return stop;
+        }
 
-        visited[start.0 as usize][start.1 as usize] |= (2usize).pow(dir as u32);
+        let reach = if stop == EXIT {
+            isize::MAX
+        } else {
+            let (sr, sc) = self.pos(stop);
+            (sr - r).abs() + (sc - c).abs()
+        };
 
-        // traverse and update visited to detect loops
-        let mut d = dir;
-        loop {
-            let bin = (2usize).pow((d % 4) as u32);
-            let dir = DIRS[d % 4];
+        if ahead > reach {
+            return stop;
+        }
 
-            loop {
-                let next = (cur.0 + dir.0, cur.1 + dir.1);
+        self.index((r + dir.0 * (ahead - 1), c + dir.1 * (ahead - 1))) as u32
+    }
 
-                if next == obstacle {
-                    break;
-                }
+    /**
+     * jumps from wall to wall with `obstacle` added; it's a loop if we stop
+     * somewhere we've already stopped facing the same way. `seen` holds a
+     * bitmask of directions per cell, and is left cleared for the next try
+     */
+    fn loops(&self, obstacle: (isize, isize), seen: &mut [u8]) -> bool {
+        let mut cell = self.index(self.start) as u32;
+        let mut d = 0;
+        let mut touched = vec![];
+
+        let looped = loop {
+            let next = self.jump(cell, d, obstacle);
+
+            if next == EXIT {
+                break false;
+            }
 
-                if let Some(c) = self.get(next) {
-                    if c == '#' {
-                        // hit a wall
-                        break;
-                    }
-
-                    // first time using mutable reference?
-                    let cell = &mut visited[next.0 as usize][next.1 as usize];
-
-                    // check if the cell has the direction we're currently moving in
-                    if (*cell & bin) == bin {
-                        return true;
-                    }
-
-                    // add direction to the visited list
-                    *cell |= bin;
-                } else {
-                    // exited the map!!
-                    return false;
-                }
+            let bit = 1 << d;
 
-                cur = next;
+            if seen[next as usize] & bit != 0 {
+                break true;
             }
 
-            // change direction
-            d += 1;
+            seen[next as usize] |= bit;
+            touched.push(next);
+
+            cell = next;
+            d = (d + 1) % 4;
+        };
+
+        for cell in touched {
+            seen[cell as usize] = 0;
         }
+
+        looped
     }
-}
 
-fn part_one(grid: &mut Grid) -> usize {
-    // find the guard `^` and walk clockwise
-    let mut cur = grid.start;
-    let travelled = 'X';
-
-    // not sure if there's a better way to repeatedly iterate the DIRS
-    loop {
-        for dir in DIRS {
-            loop {
-                let next = (cur.0 + dir.0, cur.1 + dir.1);
-
-                if let Some(c) = grid.get(next) {
-                    if c == '#' {
-                        // hit a wall
-                        break;
-                    }
-                    grid.cells[next.0 as usize][next.1 as usize] = travelled;
-                } else {
-                    // exited the map!!
-                    // for row in &grid.cells {
-                    //     println!("{:?}", row);
-                    // }
-                    return grid.cells.iter().fold(0, |acc, row| {
-                        acc +
-                            row
-                                .iter()
-                                .filter(|&&cell| cell == travelled)
-                                .count()
-                    });
-                }
+    /** every (position, direction) the guard has, step by step, until leaving */
+    fn path(&self) -> Vec<((isize, isize), usize)> {
+        let mut cur = self.start;
+        let mut d = 0;
+        let mut path = vec![(cur, d)];
+        let mut seen = vec![0u8; self.jumps.len()];
+
+        loop {
+            let cell = self.index(cur);
 
-                cur = next;
+            if seen[cell] & (1 << d) != 0 {
+                panic!("The guard never leaves! {cur:?}");
             }
+            seen[cell] |= 1 << d;
+
+            let next = (cur.0 + DIRS[d].0, cur.1 + DIRS[d].1);
+
+            match self.grid.get(next) {
+                None => {
+                    return path;
+                }
+                Some('#') => {
+                    // turn on the spot
+                    d = (d + 1) % 4;
+                }
+                Some(_) => {
+                    cur = next;
+                }
+            }
+
+            path.push((cur, d));
         }
     }
-}
 
-fn part_two(grid: &Grid) -> usize {
-    // never put an obstacle at the start
-    let mut cur = grid.start;
-    // could probably just be a 2d vec...
-    let mut visited = HashSet::new();
-
-    let mut count = 0;
-    visited.insert(cur);
-
-    loop {
-        for (i, dir) in DIRS.iter().enumerate() {
-            loop {
-                let next = (cur.0 + dir.0, cur.1 + dir.1);
-
-                if let Some(c) = grid.get(next) {
-                    if c == '#' {
-                        // hit a wall
-                        break;
-                        // already assessed as an obstacle
-                    } else if !visited.contains(&next) {
-                        // try to place an obstacle
-                        if grid.try_obstacle(next, cur, i) {
-                            count += 1;
-                        }
-
-                        visited.insert(next);
-                    }
-                } else {
-                    // exited the map!!
-                    return count;
-                }
+    /** distinct positions the guard visits, in the order first visited */
+    fn visited(&self) -> Vec<(isize, isize)> {
+        let mut seen = vec![false; self.jumps.len()];
 
-                cur = next;
-            }
+        self.path()
+            .into_iter()
+            .filter_map(|(pos, _)| {
+                let cell = self.index(pos);
+
+                (!seen[cell]).then(|| {
+                    seen[cell] = true;
+                    pos
+                })
+            })
+            .collect()
+    }
+
+    /**
+     * an obstacle only matters if it's on the guard's path (but not at the start);
+     * splits those up across `threads`
+     */
+    fn obstacles(&self, threads: usize) -> Vec<(isize, isize)> {
+        let candidates = self.visited().split_off(1);
+        let chunk = candidates.len().div_ceil(threads.max(1)).max(1);
+
+        thread::scope(|scope| {
+            let handles = candidates
+                .chunks(chunk)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut seen = vec![0; self.jumps.len()];
+
+                        chunk
+                            .iter()
+                            .copied()
+                            .filter(|&obstacle| self.loops(obstacle, &mut seen))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    /**
+     * like the puzzle: `|` and `-` for the path, `+` where it turns or crosses,
+     * and `O` for obstacles
+     */
+    fn render(&self, obstacles: &[(isize, isize)]) -> String {
+        let mut cells = self.grid.cells.clone();
+        // bit 1 for up/down, 2 for left/right
+        let mut axes = vec![0; self.jumps.len()];
+
+        for (pos, d) in self.path() {
+            axes[self.index(pos)] |= 1 << (d % 2);
         }
+
+        for (r, c, _) in self.grid.iter() {
+            let cell = &mut cells[r][c];
+
+            *cell = match axes[self.index((r as isize, c as isize))] {
+                1 => '|',
+                2 => '-',
+                3 => '+',
+                _ => *cell,
+            };
+        }
+
+        for &(r, c) in obstacles {
+            cells[r as usize][c as usize] = 'O';
+        }
+
+        cells[self.start.0 as usize][self.start.1 as usize] = '^';
+
+        cells
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 }
 
+fn part_one(patrol: &Patrol) -> usize {
+    patrol.visited().len()
+}
+
+fn part_two(patrol: &Patrol) -> usize {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    patrol.obstacles(threads).len()
+}
+
 fn main() {
     let (one, two) = get_part();
     let start = Instant::now();
     let contents = fs::read_to_string("./src/input.txt").unwrap();
-    let mut grid = Grid::new(&contents);
+    let patrol = Patrol::new(&contents);
 
     if one {
         let now = Instant::now();
-        let ans = part_one(&mut grid);
+        let ans = part_one(&patrol);
         println!("Part one: {:?} {:?}", ans, now.elapsed());
     }
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&grid);
+        let ans = part_two(&patrol);
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- render threads=1`
+    if has_flag("render") {
+        let threads = flag_value("threads").unwrap_or(1);
+
+        println!("{}", patrol.render(&patrol.obstacles(threads)));
+    }
 }
 
 #[cfg(test)]
@@ -195,29 +295,69 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let mut grid = Grid::new(EXAMPLE);
-        let ans = part_one(&mut grid);
+        let patrol = Patrol::new(EXAMPLE);
+        let ans = part_one(&patrol);
 
         assert_eq!(ans, 41);
     }
 
     #[test]
     fn test_loop() {
-        let grid = Grid::new(EXAMPLE);
-        let ans = grid.try_obstacle((6, 3), grid.start, 0);
+        let patrol = Patrol::new(EXAMPLE);
+        let mut seen = vec![0; patrol.jumps.len()];
 
-        assert_eq!(ans, true);
-
-        let ans = grid.try_obstacle((5, 4), grid.start, 0);
-
-        assert_eq!(ans, false);
+        assert!(patrol.loops((6, 3), &mut seen));
+        assert!(!patrol.loops((5, 4), &mut seen));
+        assert!(seen.iter().all(|&x| x == 0));
     }
 
     #[test]
     fn test_part_two() {
-        let grid = Grid::new(EXAMPLE);
-        let ans = part_two(&grid);
+        let patrol = Patrol::new(EXAMPLE);
+        let ans = part_two(&patrol);
 
         assert_eq!(ans, 6);
+
+        let mut obstacles = patrol.obstacles(3);
+        obstacles.sort();
+
+        assert_eq!(obstacles, [(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]);
+    }
+
+    #[test]
+    fn test_render() {
+        let patrol = Patrol::new(EXAMPLE);
+        let path = patrol
+            .render(&[])
+            .replace(['|', '-', '+', '^'], "X");
+
+        // from the puzzle
+        assert_eq!(
+            path,
+            "....#.....
+....XXXXX#
+....X...X.
+..#.X...X.
+..XXXXX#X.
+..X.X.X.X.
+.#XXXXXXX.
+.XXXXXXX#.
+#XXXXXXX..
+......#X.."
+        );
+
+        assert_eq!(
+            patrol.render(&patrol.obstacles(1)),
+            "....#.....
+....+---+#
+....|...|.
+..#.|...|.
+..+-+-+#|.
+..|.|.|.|.
+.#+O^-+-+.
+.+----OO#.
+#O-O--+|..
+......#O.."
+        );
     }
 }
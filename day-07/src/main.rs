@@ -1,5 +1,115 @@
 use std::{ fs, time::Instant, vec };
-use lib::get_part;
+use lib::{ flag_value, get_part };
+
+/** something that combines the running total with the next number */
+trait Operator {
+    fn symbol(&self) -> &'static str;
+
+    /** `acc op num`, if it fits */
+    fn apply(&self, acc: usize, num: usize) -> Option<usize>;
+
+    /** what `acc` had to be for `acc op num == result`, if anything */
+    fn undo(&self, result: usize, num: usize) -> Option<usize>;
+}
+
+struct Add;
+struct Multiply;
+struct Concat;
+struct Subtract;
+struct Xor;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, acc: usize, num: usize) -> Option<usize> {
+        acc.checked_add(num)
+    }
+
+    fn undo(&self, result: usize, num: usize) -> Option<usize> {
+        result.checked_sub(num)
+    }
+}
+
+impl Operator for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, acc: usize, num: usize) -> Option<usize> {
+        acc.checked_mul(num)
+    }
+
+    // has to divide evenly; the puzzle has no zeroes, so neither do we
+    fn undo(&self, result: usize, num: usize) -> Option<usize> {
+        if num == 0 || !result.is_multiple_of(num) {
+            return None;
+        }
+
+        Some(result / num)
+    }
+}
+
+/** power of ten that shifts `acc` left of `num` */
+fn shift(num: usize) -> usize {
+    (10usize).pow(num.checked_ilog10().unwrap_or(0) + 1)
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, acc: usize, num: usize) -> Option<usize> {
+        acc.checked_mul(shift(num))?.checked_add(num)
+    }
+
+    // result has to end with num
+    fn undo(&self, result: usize, num: usize) -> Option<usize> {
+        let shift = shift(num);
+
+        if result % shift != num {
+            return None;
+        }
+
+        Some(result / shift)
+    }
+}
+
+impl Operator for Subtract {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+
+    fn apply(&self, acc: usize, num: usize) -> Option<usize> {
+        acc.checked_sub(num)
+    }
+
+    fn undo(&self, result: usize, num: usize) -> Option<usize> {
+        result.checked_add(num)
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+
+    fn apply(&self, acc: usize, num: usize) -> Option<usize> {
+        Some(acc ^ num)
+    }
+
+    fn undo(&self, result: usize, num: usize) -> Option<usize> {
+        Some(result ^ num)
+    }
+}
+
+const PART_ONE: &[&dyn Operator] = &[&Add, &Multiply];
+const PART_TWO: &[&dyn Operator] = &[&Add, &Multiply, &Concat];
+
+/** every operator we know, for picking from the command line */
+const ALL: &[&dyn Operator] = &[&Add, &Multiply, &Concat, &Subtract, &Xor];
 
 struct Equation {
     test: usize,
@@ -7,28 +117,68 @@ struct Equation {
 }
 
 impl Equation {
-    fn is_truthy(&self, get_next: impl Fn(&mut Vec<(usize, usize)>, (usize, usize)) -> ()) -> bool {
-        let len = self.numbers.len();
+    /**
+     * works backwards from the test value: the last number has to undo
+     * cleanly (divide evenly, be a suffix, ...) which cuts most branches early;
+     * returns the operators in order, left to right
+     */
+    fn solve<'o>(&self, ops: &[&'o dyn Operator]) -> Option<Vec<&'o dyn Operator>> {
+        let mut found = vec![];
 
-        // Vec<(value, next index)>
-        let mut states = vec![(self.numbers[0], 1)];
+        if self.search(ops, self.test, self.numbers.len() - 1, &mut found) {
+            found.reverse();
+            return Some(found);
+        }
 
-        while let Some((acc, i)) = states.pop() {
-            // handle finished state
-            if i == len && acc == self.test {
-                return true;
-            }
+        None
+    }
+
+    fn search<'o>(
+        &self,
+        ops: &[&'o dyn Operator],
+        result: usize,
+        i: usize,
+        found: &mut Vec<&'o dyn Operator>
+    ) -> bool {
+        if i == 0 {
+            return result == self.numbers[0];
+        }
 
-            if i == len {
+        for &op in ops {
+            let Some(previous) = op.undo(result, self.numbers[i]) else {
                 continue;
+            };
+
+            found.push(op);
+
+            if self.search(ops, previous, i - 1, found) {
+                return true;
             }
 
-            // get next states
-            get_next(&mut states, (acc, i));
+            found.pop();
         }
 
         false
     }
+
+    /** runs the operators left to right, like the puzzle says */
+    fn evaluate(&self, ops: &[&dyn Operator]) -> Option<usize> {
+        self.numbers[1..]
+            .iter()
+            .zip(ops)
+            .try_fold(self.numbers[0], |acc, (&num, op)| op.apply(acc, num))
+    }
+
+    /** e.g. `3267 = 81 + 40 * 27` */
+    fn render(&self, ops: &[&dyn Operator]) -> String {
+        let mut out = format!("{} = {}", self.test, self.numbers[0]);
+
+        for (num, op) in self.numbers[1..].iter().zip(ops) {
+            out.push_str(&format!(" {} {num}", op.symbol()));
+        }
+
+        out
+    }
 }
 
 fn get_equations(data: &str) -> Vec<Equation> {
@@ -49,49 +199,20 @@ fn get_equations(data: &str) -> Vec<Equation> {
         .collect()
 }
 
-fn part_one(equations: &Vec<Equation>) -> usize {
+fn calibration(equations: &[Equation], ops: &[&dyn Operator]) -> usize {
     equations
         .iter()
-        .filter_map(|eq| {
-            if
-                eq.is_truthy(|states: &mut Vec<(usize, usize)>, next: (usize, usize)| {
-                    let acc = next.0;
-                    let i = next.1;
-                    let num = eq.numbers[i];
-                    states.push((acc + num, i + 1));
-                    states.push((acc * num, i + 1));
-                })
-            {
-                return Some(eq.test);
-            }
-            None
-        })
+        .filter(|eq| eq.solve(ops).is_some())
+        .map(|eq| eq.test)
         .sum()
 }
 
-fn part_two(equations: &Vec<Equation>) -> usize {
-    equations
-        .iter()
-        .filter_map(|eq| {
-            if
-                eq.is_truthy(|states: &mut Vec<(usize, usize)>, next: (usize, usize)| {
-                    // same as part one
-                    let acc = next.0;
-                    let i = next.1;
-                    let num = eq.numbers[i];
-                    states.push((acc + num, i + 1));
-                    states.push((acc * num, i + 1));
-
-                    // adds concatenated to part two
-                    let concatenated = acc * (10usize).pow(num.ilog10() + 1) + num;
-                    states.push((concatenated, i + 1));
-                })
-            {
-                return Some(eq.test);
-            }
-            None
-        })
-        .sum()
+fn part_one(equations: &[Equation]) -> usize {
+    calibration(equations, PART_ONE)
+}
+
+fn part_two(equations: &[Equation]) -> usize {
+    calibration(equations, PART_TWO)
 }
 
 fn main() {
@@ -113,7 +234,29 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- ops=+,*,^`
+    if let Some(symbols) = flag_value::<String>("ops") {
+        let ops = symbols
+            .split(",")
+            .map(|symbol| {
+                *ALL.iter()
+                    .find(|op| op.symbol() == symbol)
+                    .unwrap_or_else(|| panic!("What operator is this?! {symbol}"))
+            })
+            .collect::<Vec<_>>();
+
+        for eq in equations.iter() {
+            if let Some(found) = eq.solve(&ops) {
+                // undo and apply had better agree
+                assert_eq!(eq.evaluate(&found), Some(eq.test), "{}", eq.render(&found));
+                println!("{}", eq.render(&found));
+            }
+        }
+
+        println!("Total: {}", calibration(&equations, &ops));
+    }
 }
 
 #[cfg(test)]
@@ -137,4 +280,45 @@ mod tests {
 
         assert_eq!(ans, 11387);
     }
+
+    #[test]
+    fn test_solve() {
+        let data = get_equations(EXAMPLE);
+        let solved = data
+            .iter()
+            .filter_map(|eq| eq.solve(PART_TWO).map(|ops| eq.render(&ops)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(solved, [
+            "190 = 10 * 19",
+            "3267 = 81 * 40 + 27",
+            "156 = 15 || 6",
+            "7290 = 6 * 8 || 6 * 15",
+            "192 = 17 || 8 + 14",
+            "292 = 11 + 6 * 16 + 20",
+        ]);
+
+        for eq in data.iter() {
+            if let Some(ops) = eq.solve(PART_TWO) {
+                assert_eq!(eq.evaluate(&ops), Some(eq.test));
+            }
+        }
+    }
+
+    #[test]
+    fn test_other_ops() {
+        let eq = Equation { test: 6, numbers: vec![10, 5, 1] };
+
+        assert!(eq.solve(PART_TWO).is_none());
+        assert_eq!(eq.render(&eq.solve(&[&Add, &Subtract]).unwrap()), "6 = 10 - 5 + 1");
+
+        let eq = Equation { test: 14, numbers: vec![10, 5, 1] };
+
+        assert_eq!(eq.render(&eq.solve(&[&Subtract, &Xor]).unwrap()), "14 = 10 ^ 5 - 1");
+
+        // can't go below zero going forwards
+        let eq = Equation { test: 3, numbers: vec![1, 2, 4] };
+
+        assert!(eq.solve(&[&Subtract, &Add]).is_none());
+    }
 }
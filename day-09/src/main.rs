@@ -1,133 +1,183 @@
-use std::{ fs, time::Instant };
-use lib::get_part;
-
-// build a full vector of all digits, and fill with Some(usize) or None to represent empty "."
-fn parse_data(data: &str) -> Vec<Option<usize>> {
-    let mut out = vec![];
-    let mut chars = data.chars();
-    let mut id = 0;
-
-    for &is_file in [true, false].iter().cycle() {
-        if let Some(ch) = chars.next() {
-            if let Some(digit) = ch.to_digit(10) {
-                if is_file {
-                    out.append(&mut vec![Some(id); digit as usize]);
-                    id += 1;
-                } else {
-                    // append empties
-                    out.append(&mut vec![None; digit as usize]);
-                }
+use std::{ cmp::Reverse, collections::BinaryHeap, fs, time::Instant };
+use lib::{ get_part, has_flag };
+
+/** a run of blocks on the disk */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    start: usize,
+    len: usize,
+}
+
+/** files can be in pieces after compacting blocks one at a time */
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Blocks,
+    Files,
+}
+
+#[derive(Debug, Clone)]
+struct Disk {
+    /** (file id, where it is); a fragmented file has more than one */
+    files: Vec<(usize, Span)>,
+    free: Vec<Span>,
+    size: usize,
+}
+
+impl Disk {
+    // alternating file and free lengths
+    fn new(data: &str) -> Self {
+        let mut files = vec![];
+        let mut free = vec![];
+        let mut start = 0;
+
+        for (i, len) in data.trim().chars().filter_map(|ch| ch.to_digit(10)).enumerate() {
+            let span = Span { start, len: len as usize };
+
+            if i % 2 == 0 {
+                files.push((i / 2, span));
+            } else if span.len > 0 {
+                free.push(span);
             }
-        } else {
-            break;
+
+            start += span.len;
         }
+
+        Self { files, free, size: start }
     }
 
-    out
-}
+    /** a disk with just these files, and free space everywhere else */
+    fn with_files(mut files: Vec<(usize, Span)>, size: usize) -> Self {
+        files.sort_by_key(|(_, span)| span.start);
 
-fn part_one(data: &Vec<Option<usize>>) -> usize {
-    // move files one at a time
-    let mut copy = data.clone();
-    let mut s = 0;
-    let mut e = data.len() - 1;
+        let mut free = vec![];
+        let mut start = 0;
 
-    loop {
-        // each numbers towards each other
-        while copy[s].is_some() {
-            s += 1;
+        for (_, span) in files.iter() {
+            if span.start > start {
+                free.push(Span { start, len: span.start - start });
+            }
+            start = span.start + span.len;
         }
 
-        while copy[e].is_none() {
-            e -= 1;
+        if size > start {
+            free.push(Span { start, len: size - start });
         }
 
-        if e <= s {
-            break;
-        }
+        Self { files, free, size }
+    }
 
-        // s is none; e is some; swap
-        copy.swap(s, e);
+    fn compact(&self, mode: Mode) -> Self {
+        match mode {
+            Mode::Blocks => self.compact_blocks(),
+            Mode::Files => self.compact_files(),
+        }
     }
 
-    // map_while works because there are no empty gaps in between
-    copy.iter()
-        .map_while(|&x| x)
-        .enumerate()
-        .fold(0, |acc, (i, v)| { acc + i * v })
-}
+    /** fills free space from the left with blocks from the right, splitting files up */
+    fn compact_blocks(&self) -> Self {
+        let mut files = vec![];
+        let mut free = self.free.iter().copied().peekable();
 
-fn part_two(data: &Vec<Option<usize>>) -> usize {
-    // move complete files EXACTLY ONCE from the right
-    let mut copy = data.clone();
-    // start is first None
-    let s = copy
-        .iter()
-        .position(|x| x.is_none())
-        .unwrap();
-    let mut e = data.len() - 1;
-    // keep track of which files have moved EXACTLY ONCE
-    let mut moved = vec![false; copy.last().unwrap().unwrap()];
-
-    // s never really changes here, unfortunately
-    while s < e {
-        if let Some(item) = copy[e] {
-            let need = (0..=e)
-                .rev()
-                .take_while(|&x| { copy[x] == Some(item) })
-                .count();
-
-            if moved[item - 1] {
-                e -= need;
-                continue;
-            }
+        for &(id, span) in self.files.iter().rev() {
+            let mut left = span.len;
 
-            // look from left for empty space
-            // println!("looking for: {item} of size {need}, with {s} and {e}");
+            while left > 0 {
+                let Some(gap) = free.peek_mut().filter(|gap| gap.start < span.start) else {
+                    break;
+                };
+                let moved = left.min(gap.len);
 
-            let mut cur = s;
+                files.push((id, Span { start: gap.start, len: moved }));
+                left -= moved;
+                gap.start += moved;
+                gap.len -= moved;
 
-            loop {
-                while copy[cur].is_some() {
-                    cur += 1;
-                }
-                if cur > e - need {
-                    // can't find space; move to next number from the right
-                    e -= need;
-                    break;
-                }
-                let available = copy[cur..]
-                    .iter()
-                    .take_while(|x| x.is_none())
-                    .count();
-
-                if available >= need {
-                    for i in cur..cur + need {
-                        copy.swap(i, e);
-                        // update end
-                        e -= 1;
-                    }
-
-                    moved[item - 1] = true;
-                    break;
-                } else {
-                    cur += available;
+                if gap.len == 0 {
+                    free.next();
                 }
             }
-        } else {
-            e -= 1;
+
+            // whatever didn't fit stays where it was
+            if left > 0 {
+                files.push((id, Span { start: span.start, len: left }));
+            }
         }
+
+        Self::with_files(files, self.size)
     }
 
-    copy.iter()
-        .enumerate()
-        .fold(0, |acc, (i, opt)| {
-            if let Some(v) = opt {
-                // ignore None's
-                return acc + i * v;
+    /**
+     * moves whole files, highest id first, to the leftmost gap that fits;
+     * gaps are kept in a min-heap (by start) for each size, so finding one is
+     * just peeking at each heap that's big enough
+     */
+    fn compact_files(&self) -> Self {
+        let largest = self.free
+            .iter()
+            .map(|gap| gap.len)
+            .max()
+            .unwrap_or(0);
+        let mut heaps = vec![BinaryHeap::new(); largest + 1];
+
+        for gap in self.free.iter() {
+            heaps[gap.len].push(Reverse(gap.start));
+        }
+
+        let mut files = vec![];
+
+        for &(id, span) in self.files.iter().rev() {
+            let best = (span.len..=largest)
+                .filter_map(|len| heaps[len].peek().map(|&Reverse(start)| (start, len)))
+                .filter(|&(start, _)| start < span.start)
+                .min();
+
+            let Some((start, len)) = best else {
+                files.push((id, span));
+                continue;
+            };
+
+            heaps[len].pop();
+            files.push((id, Span { start, len: span.len }));
+
+            // what's left of the gap
+            if len > span.len {
+                heaps[len - span.len].push(Reverse(start + span.len));
             }
-            acc
-        })
+        }
+
+        Self::with_files(files, self.size)
+    }
+
+    fn checksum(&self) -> usize {
+        self.files
+            .iter()
+            .map(|&(id, Span { start, len })| {
+                // id * (start + start+1 + ... + start+len-1)
+                id * (start * len + (len * len.saturating_sub(1)) / 2)
+            })
+            .sum()
+    }
+
+    /** like the puzzle: one digit per block (the file id mod 10), and "." for free */
+    fn render(&self) -> String {
+        let mut blocks = vec!['.'; self.size];
+
+        for &(id, span) in self.files.iter() {
+            let digit = char::from_digit((id % 10) as u32, 10).unwrap();
+
+            blocks[span.start..span.start + span.len].fill(digit);
+        }
+
+        blocks.into_iter().collect()
+    }
+}
+
+fn part_one(disk: &Disk) -> usize {
+    disk.compact(Mode::Blocks).checksum()
+}
+
+fn part_two(disk: &Disk) -> usize {
+    disk.compact(Mode::Files).checksum()
 }
 
 fn main() {
@@ -135,21 +185,27 @@ fn main() {
     let start = Instant::now();
     let data = fs::read_to_string("./src/input.txt").unwrap();
 
-    let puzzle = parse_data(&data);
+    let disk = Disk::new(&data);
 
     if one {
         let now = Instant::now();
-        let ans = part_one(&puzzle);
+        let ans = part_one(&disk);
         println!("Part one: {:?} {:?}", ans, now.elapsed());
     }
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&puzzle);
+        let ans = part_two(&disk);
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    if has_flag("render") {
+        println!("{}", disk.render());
+        println!("{}", disk.compact(Mode::Blocks).render());
+        println!("{}", disk.compact(Mode::Files).render());
+    }
 }
 
 #[cfg(test)]
@@ -160,17 +216,47 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let data = parse_data(EXAMPLE);
-        let ans = part_one(&data);
+        let disk = Disk::new(EXAMPLE);
+        let ans = part_one(&disk);
 
         assert_eq!(ans, 1928);
     }
 
     #[test]
     fn test_part_two() {
-        let data = parse_data(EXAMPLE);
-        let ans = part_two(&data);
+        let disk = Disk::new(EXAMPLE);
+        let ans = part_two(&disk);
 
         assert_eq!(ans, 2858);
     }
+
+    #[test]
+    fn test_render() {
+        let disk = Disk::new(EXAMPLE);
+
+        assert_eq!(disk.render(), "00...111...2...333.44.5555.6666.777.888899");
+        assert_eq!(
+            disk.compact(Mode::Blocks).render(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(
+            disk.compact(Mode::Files).render(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+
+        let disk = Disk::new("12345");
+
+        assert_eq!(disk.render(), "0..111....22222");
+        assert_eq!(disk.compact(Mode::Blocks).render(), "022111222......");
+    }
+
+    #[test]
+    fn test_trailing_free_space() {
+        let disk = Disk::new("1234");
+
+        assert_eq!(disk.render(), "0..111....");
+        assert_eq!(disk.compact(Mode::Blocks).render(), "0111......");
+        assert_eq!(disk.compact(Mode::Files).render(), "0..111....");
+        assert_eq!(part_two(&disk), 3 + 4 + 5);
+    }
 }
@@ -1,61 +1,194 @@
-use std::{ collections::HashSet, fs, time::Instant };
-use lib::{ get_part, Grid, DIRS };
+use std::{ fs, time::Instant };
+use lib::{ flag_value, get_part, has_flag, Grid, DIRS };
 
-fn parts_one_and_two<T>(
-    grid: &Grid<u32>,
-    update_complete: impl Fn(&mut HashSet<T>, Vec<(usize, usize)>) -> ()
-) -> usize {
-    // find the zeroes
-    let mut states = vec![];
-    let mut complete = HashSet::new();
-
-    for (r, c, &cell) in grid.iter() {
-        if cell == 0 {
-            states.push(vec![(r, c)]);
+/** heights go from `start` to `end`, changing by `delta` each step */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Trail {
+    start: i64,
+    end: i64,
+    delta: i64,
+}
+
+const HIKING: Trail = Trail { start: 0, end: 9, delta: 1 };
+
+impl Trail {
+    fn steps(&self) -> usize {
+        self.along(self.end)
+            .unwrap_or_else(|| {
+                panic!("can't get from {} to {} by {}", self.start, self.end, self.delta)
+            })
+    }
+
+    /** how many steps from the start a height is, if it's ever on a trail */
+    fn along(&self, height: i64) -> Option<usize> {
+        let diff = height - self.start;
+
+        if self.delta == 0 || diff % self.delta != 0 || diff / self.delta < 0 {
+            return None;
         }
+
+        Some((diff / self.delta) as usize)
     }
+}
 
-    while let Some(state) = states.pop() {
-        // finished
-        if state.len() == 10 {
-            update_complete(&mut complete, state);
-            continue;
+/**
+ * dynamic programming from the summits down: each cell knows which summits it
+ * can reach (a bitset) and how many ways it can get to them
+ */
+struct Analysis<'a> {
+    grid: &'a Grid<u32>,
+    trail: Trail,
+    /** cell -> level along the trail */
+    levels: Vec<Option<usize>>,
+    heads: Vec<(usize, usize)>,
+    reach: Vec<Vec<u64>>,
+    paths: Vec<usize>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(grid: &'a Grid<u32>, trail: Trail) -> Self {
+        let width = grid.width as usize;
+        let cells = (grid.width * grid.height) as usize;
+        let steps = trail.steps();
+
+        let mut levels = vec![None; cells];
+        let mut by_level = vec![vec![]; steps + 1];
+
+        for (r, c, &height) in grid.iter() {
+            if let Some(level) = trail.along(height as i64).filter(|&l| l <= steps) {
+                levels[r * width + c] = Some(level);
+                by_level[level].push((r, c));
+            }
         }
 
-        // get next states
-        // next number is the length of state
-        let next = state.len();
-        let pos = state[next - 1];
+        let summits = by_level[steps].len();
+        let mut reach = vec![vec![]; cells];
+        let mut paths = vec![0; cells];
+
+        for (i, &(r, c)) in by_level[steps].iter().enumerate() {
+            let cell = r * width + c;
+
+            reach[cell] = vec![0; summits.div_ceil(64)];
+            reach[cell][i / 64] |= 1 << (i % 64);
+            paths[cell] = 1;
+        }
+
+        for level in (0..steps).rev() {
+            for &(r, c) in by_level[level].iter() {
+                let cell = r * width + c;
+                let mut summits = vec![0; summits.div_ceil(64)];
+
+                for next in neighbours(grid, (r, c)) {
+                    let next = next.0 * width + next.1;
 
-        // search all directions for next
-        for dir in DIRS {
-            let n = ((pos.0 as isize) + dir.0, (pos.1 as isize) + dir.1);
+                    if levels[next] != Some(level + 1) {
+                        continue;
+                    }
 
-            if let Some(&v) = grid.get(n) {
-                if v == (next as u32) {
-                    let mut clone = state.clone();
-                    clone.push((n.0 as usize, n.1 as usize));
-                    states.push(clone);
+                    for (word, other) in summits.iter_mut().zip(reach[next].iter()) {
+                        *word |= other;
+                    }
+                    paths[cell] += paths[next];
                 }
+
+                reach[cell] = summits;
+            }
+        }
+
+        let heads = by_level.swap_remove(0);
+
+        Self { grid, trail, levels, heads, reach, paths }
+    }
+
+    fn index(&self, (r, c): (usize, usize)) -> usize {
+        r * (self.grid.width as usize) + c
+    }
+
+    /** how many summits a trailhead can reach */
+    fn score(&self, head: (usize, usize)) -> usize {
+        self.reach[self.index(head)]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /** how many different trails start at a trailhead */
+    fn rating(&self, head: (usize, usize)) -> usize {
+        self.paths[self.index(head)]
+    }
+
+    /** every trail from `head`; only steps onto cells that still lead to a summit */
+    fn trails(&self, head: (usize, usize)) -> Vec<Vec<(usize, usize)>> {
+        let mut trails = vec![];
+
+        if self.levels[self.index(head)] == Some(0) {
+            self.walk(&mut vec![head], &mut trails);
+        }
+
+        trails
+    }
+
+    fn walk(&self, trail: &mut Vec<(usize, usize)>, trails: &mut Vec<Vec<(usize, usize)>>) {
+        let pos = *trail.last().unwrap();
+
+        if trail.len() == self.trail.steps() + 1 {
+            trails.push(trail.clone());
+            return;
+        }
+
+        for next in neighbours(self.grid, pos) {
+            let cell = self.index(next);
+
+            if self.levels[cell] == Some(trail.len()) && self.paths[cell] > 0 {
+                trail.push(next);
+                self.walk(trail, trails);
+                trail.pop();
             }
         }
     }
 
-    complete.len()
+    /** the map with only `trail`'s heights showing, like the puzzle */
+    fn render(&self, trail: &[(usize, usize)]) -> String {
+        let mut rows = vec![vec!['.'; self.grid.width as usize]; self.grid.height as usize];
+
+        for &(r, c) in trail {
+            rows[r][c] = char::from_digit(self.grid.cells[r][c], 10).unwrap();
+        }
+
+        rows.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-fn part_one(grid: &Grid<u32>) -> usize {
-    parts_one_and_two(grid, |complete, state| {
-        // 0's and 9's need to be unique
-        complete.insert((state[0], state[9]));
+fn neighbours(
+    grid: &Grid<u32>,
+    (r, c): (usize, usize)
+) -> impl Iterator<Item = (usize, usize)> + '_ {
+    DIRS.iter().filter_map(move |dir| {
+        let n = ((r as isize) + dir.0, (c as isize) + dir.1);
+
+        grid.get(n).map(|_| (n.0 as usize, n.1 as usize))
     })
 }
 
+fn part_one(grid: &Grid<u32>) -> usize {
+    let analysis = Analysis::new(grid, HIKING);
+
+    analysis.heads
+        .iter()
+        .map(|&head| analysis.score(head))
+        .sum()
+}
+
 fn part_two(grid: &Grid<u32>) -> usize {
-    parts_one_and_two(grid, |complete, state| {
-        // each node needs to be unique
-        complete.insert(state);
-    })
+    let analysis = Analysis::new(grid, HIKING);
+
+    analysis.heads
+        .iter()
+        .map(|&head| analysis.rating(head))
+        .sum()
 }
 
 fn main() {
@@ -77,7 +210,33 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- head=0 render` or `start=9 end=0 delta=-1`
+    let trail = Trail {
+        start: flag_value("start").unwrap_or(HIKING.start),
+        end: flag_value("end").unwrap_or(HIKING.end),
+        delta: flag_value("delta").unwrap_or(HIKING.delta),
+    };
+    let analysis = Analysis::new(&grid, trail);
+
+    if let Some(head) = flag_value::<usize>("head") {
+        let head = analysis.heads[head];
+        let trails = analysis.trails(head);
+
+        println!("{head:?}: score {}, rating {}", analysis.score(head), analysis.rating(head));
+
+        if has_flag("render") {
+            for trail in trails {
+                println!("{}\n", analysis.render(&trail));
+            }
+        }
+    } else if trail != HIKING {
+        let scores = analysis.heads.iter().map(|&h| analysis.score(h)).sum::<usize>();
+        let ratings = analysis.heads.iter().map(|&h| analysis.rating(h)).sum::<usize>();
+
+        println!("{trail:?}: scores {scores}, ratings {ratings}");
+    }
 }
 
 #[cfg(test)]
@@ -126,4 +285,48 @@ mod tests {
 
         assert_eq!(ans, 81);
     }
+
+    #[test]
+    fn test_each_head() {
+        let grid = Grid::new_with_u32(EXAMPLE);
+        let analysis = Analysis::new(&grid, HIKING);
+        let scores = analysis.heads
+            .iter()
+            .map(|&h| analysis.score(h))
+            .collect::<Vec<_>>();
+        let ratings = analysis.heads
+            .iter()
+            .map(|&h| analysis.rating(h))
+            .collect::<Vec<_>>();
+
+        // from the puzzle, in reading order
+        assert_eq!(scores, [5, 6, 5, 3, 1, 3, 5, 3, 5]);
+        assert_eq!(ratings, [20, 24, 10, 4, 1, 4, 5, 8, 5]);
+
+        // downhill: summits reach the same pairs, and there are as many trails
+        let down = Analysis::new(&grid, Trail { start: 9, end: 0, delta: -1 });
+        let scores: usize = down.heads.iter().map(|&h| down.score(h)).sum();
+        let ratings: usize = down.heads.iter().map(|&h| down.rating(h)).sum();
+
+        assert_eq!((scores, ratings), (36, 81));
+    }
+
+    #[test]
+    fn test_trails() {
+        let grid = Grid::new_with_u32("0123\n1234");
+        let analysis = Analysis::new(&grid, Trail { start: 0, end: 3, delta: 1 });
+        let mut trails = analysis.trails((0, 0));
+
+        trails.sort();
+
+        assert_eq!(analysis.score((0, 0)), 2);
+        assert_eq!(analysis.rating((0, 0)), 4);
+        assert_eq!(trails, [
+            vec![(0, 0), (0, 1), (0, 2), (0, 3)],
+            vec![(0, 0), (0, 1), (0, 2), (1, 2)],
+            vec![(0, 0), (0, 1), (1, 1), (1, 2)],
+            vec![(0, 0), (1, 0), (1, 1), (1, 2)],
+        ]);
+        assert_eq!(analysis.render(&trails[1]), "012.\n..3.");
+    }
 }
@@ -1,79 +1,137 @@
-use std::{ collections::HashMap, fs, mem, time::Instant };
-use lib::get_part;
+use std::{ collections::{ HashMap, HashSet }, fs, mem, time::Instant };
+use lib::{ flag_value, get_part };
 
-fn parse_data(data: &str) -> Vec<usize> {
+fn parse_data(data: &str) -> Vec<u128> {
     // first time trimming and usng filter_map with `ok`
     data.trim()
         .split(" ")
-        .filter_map(|x| { x.parse::<usize>().ok() })
+        .filter_map(|x| { x.parse::<u128>().ok() })
         .collect()
 }
 
-// wow, first macro (to cut 5 lines down to 1)
-macro_rules! update_or_create {
-    ($map:expr, $key:expr, $val:expr) => {
-        {
-            $map.entry($key)
-                    .and_modify(|x| {
-                        *x += $val;
-                    })
-                    .or_insert($val);
+/** one way a stone can change when you blink */
+trait Rule {
+    /** pushes what `stone` becomes onto `out`; false if this rule doesn't apply */
+    fn apply(&self, stone: u128, out: &mut Vec<u128>) -> bool;
+}
+
+/** 0 becomes 1 */
+struct Zero;
+
+/** an even number of digits splits into the left and right halves */
+struct SplitEven;
+
+/** anything else gets multiplied */
+struct Multiply(u128);
+
+impl Rule for Zero {
+    fn apply(&self, stone: u128, out: &mut Vec<u128>) -> bool {
+        if stone != 0 {
+            return false;
         }
-    };
+        out.push(1);
+
+        true
+    }
 }
 
-fn get_stone_count(data: &Vec<usize>, blinks: usize) -> usize {
-    // numbers->counts
-    let mut current: HashMap<usize, usize> = HashMap::new();
+impl Rule for SplitEven {
+    fn apply(&self, stone: u128, out: &mut Vec<u128>) -> bool {
+        // get number of digits in the number
+        let digits = stone.checked_ilog10().unwrap_or(0) + 1;
+
+        if !digits.is_multiple_of(2) {
+            return false;
+        }
+
+        let pow = (10u128).pow(digits / 2);
+
+        out.push(stone / pow);
+        out.push(stone % pow);
 
-    for &datum in data {
-        update_or_create!(current, datum, 1);
+        true
+    }
+}
+
+impl Rule for Multiply {
+    fn apply(&self, stone: u128, out: &mut Vec<u128>) -> bool {
+        out.push(stone.checked_mul(self.0).expect("stone too big for a u128"));
+
+        true
+    }
+}
+
+/** the first rule that applies wins */
+fn puzzle_rules() -> Vec<Box<dyn Rule>> {
+    vec![Box::new(Zero), Box::new(SplitEven), Box::new(Multiply(2024))]
+}
+
+/** what happened over a number of blinks */
+#[derive(Debug, PartialEq)]
+struct Blinks {
+    count: u128,
+    /** distinct stone values before the first blink, and after each one */
+    distinct: Vec<usize>,
+    /** the first blink that didn't turn up a value we hadn't seen before */
+    closed: Option<usize>,
+}
+
+/** stones only ever depend on their own value, so just count each value */
+fn blink(stones: &[u128], blinks: usize, rules: &[Box<dyn Rule>]) -> Blinks {
+    // numbers->counts
+    let mut current: HashMap<u128, u128> = HashMap::new();
+    let mut next: HashMap<u128, u128> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut out = vec![];
+
+    for &stone in stones {
+        *current.entry(stone).or_insert(0) += 1;
+        seen.insert(stone);
     }
 
+    let mut distinct = vec![current.len()];
+    let mut closed = None;
+
     // take x blinks into eternity
-    for _ in (0..).take(blinks) {
-        // had to make sure these were the same types
-        let mut next: HashMap<usize, usize> = HashMap::new();
-
-        for (&k, &v) in current.iter() {
-            if k == 0 {
-                // pass the same number of items into the "1" key
-                update_or_create!(next, 1, v);
-                continue;
+    for step in 1..=blinks {
+        for (&stone, &count) in current.iter() {
+            out.clear();
+
+            if !rules.iter().any(|rule| rule.apply(stone, &mut out)) {
+                // no rule, no change
+                out.push(stone);
             }
 
-            // get number of digits in the number
-            let digits = k.ilog10() + 1;
-
-            if digits % 2 == 0 {
-                // split into 2
-                let split = digits / 2;
-                let pow = (10usize).pow(split);
-                let left = k / pow;
-                let right = k - left * pow;
-
-                update_or_create!(next, left, v);
-                update_or_create!(next, right, v);
-            } else {
-                // just multiply by 2024
-                update_or_create!(next, k * 2024, v);
+            for &new in out.iter() {
+                *next.entry(new).or_insert(0) += count;
             }
         }
 
-        // first mem::swap
+        // reuse the old map instead of making a new one each blink
         mem::swap(&mut current, &mut next);
+        next.clear();
+
+        let before = seen.len();
+
+        seen.extend(current.keys());
+        distinct.push(current.len());
+
+        // everything from here on has already been seen, and so have its children
+        if closed.is_none() && seen.len() == before {
+            closed = Some(step);
+        }
     }
 
-    current.values().sum()
+    Blinks { count: current.values().sum(), distinct, closed }
 }
 
 // FYI: naive solution here: https://gist.github.com/bozdoz/934ee5fad305507e46b2367ef1e1e00c
-fn part_one(data: &Vec<usize>) -> usize {
-    get_stone_count(data, 25)
+fn part_one(data: &[u128]) -> u128 {
+    blink(data, 25, &puzzle_rules()).count
 }
 
-fn part_two(data: &Vec<usize>) -> usize {
-    get_stone_count(data, 75)
+fn part_two(data: &[u128]) -> u128 {
+    blink(data, 75, &puzzle_rules()).count
 }
 
 fn main() {
@@ -94,7 +152,16 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- blinks=500`
+    if let Some(blinks) = flag_value("blinks") {
+        let blinks = blink(&data, blinks, &puzzle_rules());
+
+        println!("Stones: {}", blinks.count);
+        println!("Distinct per blink: {:?}", blinks.distinct);
+        println!("No new values from blink: {:?}", blinks.closed);
+    }
 }
 
 #[cfg(test)]
@@ -109,4 +176,47 @@ mod tests {
 
         assert_eq!(ans, 55312);
     }
+
+    #[test]
+    fn test_blink() {
+        let blinks = blink(&[125, 17], 6, &puzzle_rules());
+
+        assert_eq!(blinks.count, 22);
+        // 125 17 -> 253000 1 7 -> 253 0 2024 14168 -> 512072 1 20 24 28676032
+        assert_eq!(&blinks.distinct[..4], [2, 3, 4, 5]);
+        assert_eq!(blinks.closed, None);
+    }
+
+    #[test]
+    fn test_closed() {
+        let blinks = blink(&parse_data(EXAMPLE), 200, &puzzle_rules());
+
+        // way past a u64
+        assert!(blinks.count > (u64::MAX as u128));
+        assert!(blinks.closed.is_some_and(|step| step < 200));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // halve evens, leave odds
+        struct Halve;
+
+        impl Rule for Halve {
+            fn apply(&self, stone: u128, out: &mut Vec<u128>) -> bool {
+                if stone % 2 == 1 {
+                    return false;
+                }
+                out.extend([stone / 2, stone / 2]);
+
+                true
+            }
+        }
+
+        let blinks = blink(&[8, 3], 4, &[Box::new(Halve)]);
+
+        // 8 -> 4 4 -> 2 2 2 2 -> 1 x8, and the 3 never changes
+        assert_eq!(blinks.count, 8 + 1);
+        assert_eq!(blinks.distinct, [2, 2, 2, 2, 2]);
+        assert_eq!(blinks.closed, Some(4));
+    }
 }
@@ -1,10 +1,10 @@
 #![allow(non_snake_case)]
 
-use std::{ cmp::Ordering, fs, ops::{ Add, Div, Mul }, time::Instant };
-use lib::get_part;
+use std::{ fs, time::Instant };
+use lib::{ flag_value, get_part };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct Point(usize, usize);
+struct Point(i128, i128);
 
 struct Machine {
     A: Point,
@@ -12,6 +12,27 @@ struct Machine {
     P: Point,
 }
 
+/** what each press costs, and how many presses each button gets */
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    a_cost: i128,
+    b_cost: i128,
+    limit: Option<i128>,
+}
+
+const PART_ONE: Rules = Rules { a_cost: 3, b_cost: 1, limit: Some(100) };
+const PART_TWO: Rules = Rules { a_cost: 3, b_cost: 1, limit: None };
+
+impl Rules {
+    fn cost(&self, (a, b): (i128, i128)) -> i128 {
+        a * self.a_cost + b * self.b_cost
+    }
+
+    fn allows(&self, (a, b): (i128, i128)) -> bool {
+        a >= 0 && b >= 0 && self.limit.is_none_or(|limit| a <= limit && b <= limit)
+    }
+}
+
 fn parse_data(data: &str) -> Vec<Machine> {
     data.split("\n\n")
         .map(|block| {
@@ -20,7 +41,7 @@ fn parse_data(data: &str) -> Vec<Machine> {
                 .map(|l| {
                     let (x, y) = l.split_once("X").unwrap().1.split_once(", Y").unwrap();
 
-                    (x[1..].parse::<usize>().unwrap(), y[1..].parse::<usize>().unwrap())
+                    (x[1..].parse::<i128>().unwrap(), y[1..].parse::<i128>().unwrap())
                 })
                 .collect();
 
@@ -33,74 +54,28 @@ fn parse_data(data: &str) -> Vec<Machine> {
         .collect()
 }
 
-impl Add for Point {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Point(self.0 + rhs.0, self.1 + rhs.1)
-    }
+fn cross(a: Point, b: Point) -> i128 {
+    a.0 * b.1 - a.1 * b.0
 }
 
-impl Div for Point {
-    type Output = Self;
-
-    fn div(self, rhs: Self) -> Self::Output {
-        Point(self.0 / rhs.0, self.1 / rhs.1)
+// from chatgpt (via day 14)
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if a == 0 {
+        return (b, 0, 1);
     }
-}
+    let (gcd, x1, y1) = extended_gcd(b % a, a);
+    let x = y1 - (b / a) * x1;
+    let y = x1;
 
-impl Mul<usize> for Point {
-    type Output = Self;
-    fn mul(self, rhs: usize) -> Self::Output {
-        Point(self.0 * rhs, self.1 * rhs)
-    }
+    (gcd, x, y)
 }
 
-impl PartialOrd for Point {
-    fn gt(&self, other: &Self) -> bool {
-        self.0 > other.0 && self.1 > other.1
-    }
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if *self > *other {
-            return Some(Ordering::Greater);
-        }
-        None
-    }
+/** rounds up, for a positive `d` */
+fn div_ceil(n: i128, d: i128) -> i128 {
+    -(-n).div_euclid(d)
 }
 
 impl Machine {
-    // worked for part 1
-    fn _least_tokens(&self) -> Option<Point> {
-        // start with maximum B
-        let max_a = {
-            let a = self.P / self.A;
-            a.0.min(a.1)
-        };
-        let b = self.P / self.B;
-        // take the lowest possible in b and multiply B
-        let mut lowest_b = b.0.min(b.1);
-        let mut lowest_a = 0;
-
-        while lowest_a <= max_a {
-            let eh = self.A * lowest_a;
-            let bee = self.B * lowest_b;
-            let sum = eh + bee;
-
-            if sum == self.P {
-                return Some(Point(lowest_a, lowest_b));
-            }
-
-            if sum > self.P {
-                // decrement b
-                lowest_b -= 1;
-            } else {
-                lowest_a += 1;
-            }
-        }
-
-        None
-    }
-
     /**
 
 u/CorvusCalvaria
@@ -120,61 +95,118 @@ This can be represented as a pair of straight lines, pointing down and to the ri
 
 Where the intersection of these lines (i_x, i_y)tells you that you need i_x A presses and i_y B presses to reach the prize. If i_x or i_y is negative or not a whole number, then there's no solution (orange dots).
 
+If the lines are parallel (A and B point the same way), there's either no
+intersection or a whole line of them; see `collinear`.
+
  */
-    fn least_tokens_v2(&self) -> Option<Point> {
-        // get intersection of lines
-        // cross multiplication rule
-        // I don't know why these are negative
-        let a1 = -(self.A.0 as f64);
-        let a2 = -(self.A.1 as f64);
-        let b1 = -(self.B.0 as f64);
-        let b2 = -(self.B.1 as f64);
-        let p1 = self.P.0 as f64;
-        let p2 = self.P.1 as f64;
-
-        let x = (b1 * p2 - b2 * p1) / (a1 * b2 - a2 * b1);
-        let y = (p1 * a2 - p2 * a1) / (a1 * b2 - a2 * b1);
-
-        // find negative
-        if x < 0.0 || y < 0.0 {
-            return None;
+    fn presses(&self, rules: &Rules) -> Option<(i128, i128)> {
+        // cramer's rule, but in integers so nothing gets rounded
+        let det = cross(self.A, self.B);
+
+        if det == 0 {
+            return self.collinear(rules);
         }
 
-        // find decimals
-        if x == x.trunc() && y == y.trunc() {
-            // no decimals?
-            return Some(Point(x as usize, y as usize));
+        let a = cross(self.P, self.B);
+        let b = cross(self.A, self.P);
+
+        if a % det != 0 || b % det != 0 {
+            return None;
         }
 
-        None
+        let presses = (a / det, b / det);
+
+        rules.allows(presses).then_some(presses)
     }
-}
 
-fn part_one(machines: &Vec<Machine>) -> usize {
-    let mut cost = 0;
-    for machine in machines.iter() {
-        if let Some(point) = machine.least_tokens_v2() {
-            cost += point.0 * 3 + point.1;
+    /**
+     * A and B are on the same line, so the prize has to be too; then it's
+     * `a * u + b * v = w` along that line. Every whole solution is
+     * `(a0 + k * v / g, b0 - k * u / g)`, and since the cost is linear in `k`,
+     * the cheapest is at one end of the allowed range of `k`
+     */
+    fn collinear(&self, rules: &Rules) -> Option<(i128, i128)> {
+        let on_line = if self.A != Point(0, 0) {
+            cross(self.A, self.P) == 0
+        } else if self.B != Point(0, 0) {
+            cross(self.B, self.P) == 0
+        } else {
+            self.P == Point(0, 0)
+        };
+
+        if !on_line {
+            return None;
         }
+
+        // x, unless nothing moves along x
+        let (u, v, w) = if self.A.0 != 0 || self.B.0 != 0 {
+            (self.A.0, self.B.0, self.P.0)
+        } else {
+            (self.A.1, self.B.1, self.P.1)
+        };
+
+        // a button that does nothing is never worth pressing
+        let presses = match (u, v) {
+            (0, 0) => (w == 0).then_some((0, 0)),
+            (0, v) => (w % v == 0).then_some((0, w / v)),
+            (u, 0) => (w % u == 0).then_some((w / u, 0)),
+            (u, v) => {
+                let (g, x, y) = extended_gcd(u, v);
+
+                if w % g != 0 {
+                    return None;
+                }
+
+                let (a0, b0) = (x * (w / g), y * (w / g));
+                let (step_a, step_b) = (v / g, u / g);
+
+                // a >= 0 and b >= 0
+                let mut low = div_ceil(-a0, step_a);
+                let mut high = b0.div_euclid(step_b);
+
+                if let Some(limit) = rules.limit {
+                    low = low.max(div_ceil(b0 - limit, step_b));
+                    high = high.min((limit - a0).div_euclid(step_a));
+                }
+
+                if low > high {
+                    return None;
+                }
+
+                let at = |k: i128| (a0 + k * step_a, b0 - k * step_b);
+                let slope = rules.a_cost * step_a - rules.b_cost * step_b;
+
+                Some(at(if slope > 0 { low } else { high }))
+            }
+        }?;
+
+        rules.allows(presses).then_some(presses)
     }
+}
 
-    cost
+/** total tokens for every winnable prize */
+fn tokens(machines: &[Machine], rules: &Rules, offset: i128) -> i128 {
+    machines
+        .iter()
+        .filter_map(|machine| {
+            let machine = Machine {
+                A: machine.A,
+                B: machine.B,
+                P: Point(machine.P.0 + offset, machine.P.1 + offset),
+            };
+
+            machine.presses(rules)
+        })
+        .map(|presses| rules.cost(presses))
+        .sum()
 }
 
-fn part_two(machines: &Vec<Machine>) -> usize {
-    let mut cost = 0;
-    for machine in machines {
-        let thousand = Machine {
-            A: machine.A,
-            B: machine.B,
-            P: Point(machine.P.0 + 10000000000000, machine.P.1 + 10000000000000),
-        };
+fn part_one(machines: &[Machine]) -> i128 {
+    tokens(machines, &PART_ONE, 0)
+}
 
-        if let Some(point) = thousand.least_tokens_v2() {
-            cost += point.0 * 3 + point.1;
-        }
-    }
-    cost
+fn part_two(machines: &[Machine]) -> i128 {
+    tokens(machines, &PART_TWO, 10000000000000)
 }
 
 fn main() {
@@ -195,7 +227,18 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- limit=50 a_cost=1 b_cost=3`
+    if let Some(limit) = flag_value("limit") {
+        let rules = Rules {
+            a_cost: flag_value("a_cost").unwrap_or(3),
+            b_cost: flag_value("b_cost").unwrap_or(1),
+            limit: (limit > 0).then_some(limit),
+        };
+
+        println!("Custom: {:?}", tokens(&machines, &rules, flag_value("offset").unwrap_or(0)));
+    }
 }
 
 #[cfg(test)]
@@ -210,4 +253,48 @@ mod tests {
 
         assert_eq!(ans, 480);
     }
+
+    #[test]
+    fn test_presses() {
+        let machines = parse_data(EXAMPLE);
+        let presses = machines
+            .iter()
+            .map(|m| m.presses(&PART_ONE))
+            .collect::<Vec<_>>();
+
+        assert_eq!(presses, [Some((80, 40)), None, Some((38, 86)), None]);
+
+        // the 2nd and 4th are winnable in part two
+        let ans = part_two(&machines);
+
+        assert_eq!(ans, 875318608908);
+    }
+
+    #[test]
+    fn test_collinear() {
+        let machine = |a: Point, b: Point, p: Point| Machine { A: a, B: b, P: p };
+
+        // B moves three times as far for a third of the cost
+        let m = machine(Point(1, 1), Point(3, 3), Point(10, 10));
+
+        assert_eq!(m.presses(&PART_TWO), Some((1, 3)));
+        assert_eq!(m.presses(&Rules { a_cost: 1, b_cost: 10, limit: None }), Some((10, 0)));
+        assert_eq!(m.presses(&Rules { a_cost: 1, b_cost: 10, limit: Some(5) }), Some((4, 2)));
+
+        // only multiples of 2 reachable
+        let m = machine(Point(4, 2), Point(6, 3), Point(11, 5));
+
+        assert_eq!(m.presses(&PART_TWO), None);
+
+        // off the line
+        let m = machine(Point(1, 2), Point(2, 4), Point(3, 5));
+
+        assert_eq!(m.presses(&PART_TWO), None);
+
+        // huge, but exact
+        let m = machine(Point(2, 0), Point(3, 0), Point(10000000000001, 0));
+
+        assert_eq!(m.presses(&PART_TWO), Some((1, 3333333333333)));
+        assert_eq!(m.presses(&PART_ONE), None);
+    }
 }
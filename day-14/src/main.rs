@@ -6,7 +6,7 @@ use std::{
     time::Instant,
     vec,
 };
-use lib::get_part;
+use lib::{ flag_value, get_part };
 use regex::Regex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -42,8 +42,8 @@ impl Display for World<'_> {
                 row.push(
                     map
                         .get(&Point(c, r))
-                        .and_then(|v| {
-                            if should_fill { Some(fill.to_string()) } else { Some(v.to_string()) }
+                        .map(|v| {
+                            if should_fill { fill.to_string() } else { v.to_string() }
                         })
                         .unwrap_or_else(|| {
                             if should_fill { " ".to_string() } else { ".".to_string() }
@@ -72,20 +72,153 @@ impl World<'_> {
             .collect()
     }
 
+    fn image(&self, time: usize) -> Image {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut pixels = vec![EMPTY; width * height];
+
+        for robot in self.after(time) {
+            let Point(x, y) = robot.position;
+            pixels[(y as usize) * width + (x as usize)] = ROBOT;
+        }
+
+        Image { width, height, pixels }
+    }
+
+    /** `count` seconds from `start`, tiled into one image */
+    fn sheet(&self, start: usize, count: usize) -> Image {
+        let frames = (start..start + count).map(|t| self.image(t)).collect::<Vec<_>>();
+        // roughly square
+        let columns = (count as f64).sqrt().ceil() as usize;
+
+        Image::sheet(&frames, columns)
+    }
+
     fn wrap(&self, position: Point) -> Point {
         let (mut x, mut y) = (position.0 % self.width, position.1 % self.height);
 
         if x < 0 {
-            x = self.width + x;
+            x += self.width;
         }
         if y < 0 {
-            y = self.height + y;
+            y += self.height;
         }
 
         Point(x, y)
     }
 }
 
+/** grayscale, one byte per pixel; robots are black on white */
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+const ROBOT: u8 = 0;
+const EMPTY: u8 = 255;
+// between frames on a sheet
+const GUTTER: u8 = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Pbm,
+    Pgm,
+    #[cfg(feature = "png")]
+    Png,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "pbm" => Format::Pbm,
+            "pgm" => Format::Pgm,
+            #[cfg(feature = "png")]
+            "png" => Format::Png,
+            #[cfg(not(feature = "png"))]
+            "png" => panic!("build with `--features png` for png"),
+            _ => panic!("what format is this? {name}"),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Pbm => "pbm",
+            Format::Pgm => "pgm",
+            #[cfg(feature = "png")]
+            Format::Png => "png",
+        }
+    }
+}
+
+impl Image {
+    /** binary pgm (P5) */
+    fn to_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend(&self.pixels);
+        out
+    }
+
+    /** binary pbm (P4): 1 is black, rows padded to whole bytes */
+    fn to_pbm(&self) -> Vec<u8> {
+        let mut out = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+
+        for row in self.pixels.chunks(self.width) {
+            for byte in row.chunks(8) {
+                let bits = byte
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |acc, (i, &p)| acc | (((p < 128) as u8) << (7 - i)));
+                out.push(bits);
+            }
+        }
+
+        out
+    }
+
+    #[cfg(feature = "png")]
+    fn to_png(&self) -> Vec<u8> {
+        let mut out = vec![];
+        let mut encoder = png::Encoder::new(&mut out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&self.pixels).unwrap();
+        writer.finish().unwrap();
+
+        out
+    }
+
+    fn encode(&self, format: Format) -> Vec<u8> {
+        match format {
+            Format::Pbm => self.to_pbm(),
+            Format::Pgm => self.to_pgm(),
+            #[cfg(feature = "png")]
+            Format::Png => self.to_png(),
+        }
+    }
+
+    /** lays `frames` out left to right, top to bottom, with a 1px gutter */
+    fn sheet(frames: &[Image], columns: usize) -> Self {
+        let (w, h) = (frames[0].width, frames[0].height);
+        let rows = frames.len().div_ceil(columns);
+        let width = columns * (w + 1) - 1;
+        let height = rows * (h + 1) - 1;
+        let mut pixels = vec![GUTTER; width * height];
+
+        for (i, frame) in frames.iter().enumerate() {
+            let (top, left) = ((i / columns) * (h + 1), (i % columns) * (w + 1));
+
+            for (r, row) in frame.pixels.chunks(w).enumerate() {
+                let start = (top + r) * width + left;
+                pixels[start..start + w].copy_from_slice(row);
+            }
+        }
+
+        Self { width, height, pixels }
+    }
+}
+
 #[derive(Debug)]
 struct Robot {
     position: Point,
@@ -160,20 +293,20 @@ fn part_one(world: &World) -> usize {
 }
 
 // from chatgpt
-fn _extended_gcd(a: isize, b: isize) -> (isize, isize, isize) {
+fn extended_gcd(a: isize, b: isize) -> (isize, isize, isize) {
     if a == 0 {
         return (b, 0, 1);
     }
-    let (gcd, x1, y1) = _extended_gcd(b % a, a);
+    let (gcd, x1, y1) = extended_gcd(b % a, a);
     let x = y1 - (b / a) * x1;
     let y = x1;
 
-    return (gcd, x, y);
+    (gcd, x, y)
 }
 
 // from chatgpt
-fn _modular_inverse(a: isize, m: isize) -> Option<isize> {
-    let (gcd, x, _) = _extended_gcd(a, m);
+fn modular_inverse(a: isize, m: isize) -> Option<isize> {
+    let (gcd, x, _) = extended_gcd(a, m);
     if gcd != 1 {
         return None;
     }
@@ -181,28 +314,51 @@ fn _modular_inverse(a: isize, m: isize) -> Option<isize> {
     Some(((x % m) + m) % m)
 }
 
-fn _chinese_remainder_theorem(given: &Vec<(isize, isize)>) -> isize {
+/** None if the moduli aren't coprime */
+fn chinese_remainder_theorem(given: &[(isize, isize)]) -> Option<isize> {
     let m: isize = given
         .iter()
         .map(|x| x.1)
         .product();
 
-    let mi: Vec<isize> = given
-        .iter()
-        .map(|x| m / x.1)
-        .collect();
+    let mut sum = 0;
 
-    let sum: isize = given
-        .iter()
-        .zip(mi.iter())
-        .map(|(a, &b)| {
-            let i = _modular_inverse(b, a.1).expect("The answer to be able to be calculated");
+    for &(a, modulus) in given {
+        let mi = m / modulus;
+        let i = modular_inverse(mi, modulus)?;
 
-            a.0 * b * i
-        })
-        .sum();
+        sum += a * mi * i;
+    }
 
-    sum % m
+    Some(sum % m)
+}
+
+/**
+ * n² times the variance, so it stays an integer;
+ * only used to compare, so the scale doesn't matter
+ */
+fn spread(values: impl Iterator<Item = isize>) -> isize {
+    let (n, sum, squares) = values.fold((0, 0, 0), |(n, sum, squares), v| {
+        (n + 1, sum + v, squares + v * v)
+    });
+
+    n * squares - sum * sum
+}
+
+/**
+ * x repeats every `width` seconds, so the time in 0..width where
+ * the robots are the most bunched up horizontally is when the picture is
+ */
+fn best_offset(world: &World, period: isize, coord: fn(&Point) -> isize) -> isize {
+    (0..period)
+        .min_by_key(|&time| {
+            spread(
+                world.robots
+                    .iter()
+                    .map(|r| coord(&world.wrap(r.after(time as usize))))
+            )
+        })
+        .unwrap()
 }
 
 // ! This did not work at first; chatgpt had to help multiple times
@@ -211,16 +367,21 @@ fn _chinese_remainder_theorem(given: &Vec<(isize, isize)>) -> isize {
 // chinese_remainder_theorem(&vec![x1, x2])
 
 // give me the fewest seconds elapsed to see the tree
-fn part_two() -> usize {
-    // 22 is when it appeared vertically centered
-    // 79 is when it appeared horizontally centered
-    // the other numbers are height and width (when it wraps)
-    for time in 0.. {
-        if time % 103 == 22 && time % 101 == 79 {
-            return time;
-        }
-    }
-    0
+fn part_two(world: &World) -> usize {
+    // used to be 22 (vertically centered) & 79 (horizontally centered), found by eye
+    let x = best_offset(world, world.width, |p| p.0);
+    let y = best_offset(world, world.height, |p| p.1);
+
+    chinese_remainder_theorem(&[(x, world.width), (y, world.height)]).unwrap_or_else(||
+        panic!("{} and {} need to be coprime", world.width, world.height)
+    ) as usize
+}
+
+/** the world is as big as it needs to be for every robot to fit */
+fn dimensions(robots: &[Robot]) -> (isize, isize) {
+    robots.iter().fold((0, 0), |(w, h), r| {
+        (w.max(r.position.0 + 1), h.max(r.position.1 + 1))
+    })
 }
 
 fn main() {
@@ -228,9 +389,11 @@ fn main() {
     let start = Instant::now();
     let data = fs::read_to_string("./src/input.txt").unwrap();
     let robots = parse_data(&data);
+    // e.g. `cargo run -- width=11 height=7`; otherwise guess from the robots
+    let (width, height) = dimensions(&robots);
     let world = World {
-        width: 101,
-        height: 103,
+        width: flag_value("width").unwrap_or(width),
+        height: flag_value("height").unwrap_or(height),
         robots: &robots,
     };
 
@@ -242,11 +405,48 @@ fn main() {
 
     if two {
         let now = Instant::now();
-        let ans = part_two();
+        let ans = part_two(&world);
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    write_images(&world);
+}
+
+/**
+ * e.g. `cargo run -- image=7286`, `cargo run -- sheet=0 count=400`,
+ * or `cargo run -- frames=./frames`; pick a type with `format=pbm|pgm|png`
+ */
+fn write_images(world: &World) {
+    let format = Format::from_name(&flag_value::<String>("format").unwrap_or("pgm".into()));
+    let ext = format.extension();
+
+    if let Some(time) = flag_value::<usize>("image") {
+        let path = format!("./src/frame_{time}.{ext}");
+        fs::write(&path, world.image(time).encode(format)).unwrap();
+        println!("Wrote {path}");
+    }
+
+    if let Some(time) = flag_value::<usize>("sheet") {
+        let count = flag_value("count").unwrap_or(100);
+        let path = format!("./src/sheet_{time}.{ext}");
+        fs::write(&path, world.sheet(time, count).encode(format)).unwrap();
+        println!("Wrote {path} ({count} seconds, left to right)");
+    }
+
+    // one whole period; after that it repeats
+    if let Some(dir) = flag_value::<String>("frames") {
+        fs::create_dir_all(&dir).unwrap();
+
+        let period = (world.width * world.height) as usize;
+
+        for time in 0..period {
+            let path = format!("{dir}/frame_{time:05}.{ext}");
+            fs::write(path, world.image(time).encode(format)).unwrap();
+        }
+        println!("Wrote {period} frames to {dir}");
+    }
 }
 
 #[cfg(test)]
@@ -288,9 +488,54 @@ mod tests {
 
     #[test]
     fn test_chinese() {
-        let ans = _chinese_remainder_theorem(&vec![(22, 103), (79, 101)]);
+        let ans = chinese_remainder_theorem(&[(22, 103), (79, 101)]);
+
+        assert_eq!(ans, Some(8159));
+        assert_eq!(chinese_remainder_theorem(&[(1, 4), (3, 6)]), None);
+    }
+
+    #[test]
+    fn test_images() {
+        let robots = vec![
+            Robot { position: Point(0, 0), velocity: Point(1, 0) },
+            Robot { position: Point(2, 1), velocity: Point(0, 0) }
+        ];
+        let world = World { width: 3, height: 2, robots: &robots };
+        let image = world.image(1);
+
+        assert_eq!(image.pixels, vec![EMPTY, ROBOT, EMPTY, EMPTY, EMPTY, ROBOT]);
+        assert_eq!(image.to_pgm(), b"P5\n3 2\n255\n\xff\x00\xff\xff\xff\x00".to_vec());
+        assert_eq!(image.to_pbm(), b"P4\n3 2\n\x40\x20".to_vec());
+
+        let sheet = world.sheet(0, 3);
+
+        assert_eq!((sheet.width, sheet.height), (3 * 2 + 1, 2 * 2 + 1));
+        // second frame starts after the gutter
+        assert_eq!(&sheet.pixels[4..7], &[EMPTY, ROBOT, EMPTY]);
+        assert_eq!(sheet.pixels[3], GUTTER);
+    }
+
+    #[test]
+    fn test_dimensions() {
+        assert_eq!(dimensions(&parse_data(EXAMPLE)), (11, 7));
+    }
+
+    #[test]
+    fn test_find_picture() {
+        // everyone meets in the middle at 40 seconds, going every which way
+        let robots = (0..30)
+            .map(|i| {
+                let velocity = Point((i % 5) - 2 + (i % 2) * 5, (i % 3) - 1 + (i % 4) * 2);
+                let start = Point(5 + (i % 3) - 1, 3 + (i % 2));
+
+                Robot { position: start + velocity * (77 - 40), velocity }
+            })
+            .collect::<Vec<_>>();
+        let world = World { width: 11, height: 7, robots: &robots };
+        let robots = world.after(0);
+        let world = World { robots: &robots, ..world };
 
-        assert_eq!(ans, 8159);
+        assert_eq!(part_two(&world), 40);
     }
 
     // run with `cargo test two -- --nocapture --include-ignored`
@@ -1,33 +1,55 @@
-use std::{ collections::HashMap, fmt::Display, fs, time::Instant };
-use lib::{ get_part, tup };
+use std::{ collections::HashMap, fmt::Display, fs, io::{ self, BufRead, Write }, time::Instant };
+use lib::{ flag_value, get_part, has_flag, tup };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Thing {
-    Box,
     Wall,
-    LBox,
-    RBox,
+    // index into `Map.boxes`
+    Box(usize),
 }
 
+/** a box covers every cell from its top-left `pos`, `size` (rows, cols) big */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Crate {
+    pos: (isize, isize),
+    size: (isize, isize),
+}
+
+impl Crate {
+    fn cells(&self) -> impl Iterator<Item = (isize, isize)> {
+        let (r, c) = self.pos;
+        let (h, w) = self.size;
+
+        (r..r + h).flat_map(move |r| (c..c + w).map(move |c| (r, c)))
+    }
+}
+
+#[derive(Clone)]
 struct Map {
     robot: (isize, isize),
     grid: HashMap<(isize, isize), Thing>,
+    boxes: Vec<Crate>,
     width: usize,
     height: usize,
-    cell_width: usize,
 }
 
 impl Map {
     fn new(data: &str) -> Self {
         let mut robot: (isize, isize) = (0, 0);
-        let mut grid: HashMap<(isize, isize), Thing> = HashMap::new();
-        let mut height = 0;
-        let mut width = 0;
+        let mut map = Self {
+            robot,
+            grid: HashMap::new(),
+            boxes: vec![],
+            width: 0,
+            height: 0,
+        };
+        // left side of a `[...]` box
+        let mut left = 0;
 
         for (r, row) in data.lines().enumerate() {
-            height += 1;
-            if width == 0 {
-                width = row.len();
+            map.height += 1;
+            if map.width == 0 {
+                map.width = row.len();
             }
             for (c, cell) in row.chars().enumerate() {
                 let pos = (r as isize, c as isize);
@@ -36,174 +58,133 @@ impl Map {
                         robot = pos;
                     }
                     '#' => {
-                        grid.insert(pos, Thing::Wall);
+                        map.grid.insert(pos, Thing::Wall);
                     }
                     'O' => {
-                        grid.insert(pos, Thing::Box);
+                        map.add_box(pos, (1, 1));
+                    }
+                    '[' => {
+                        left = pos.1;
+                    }
+                    ']' => {
+                        map.add_box((pos.0, left), (1, pos.1 - left + 1));
                     }
                     _ => {}
                 }
             }
         }
 
-        Self {
-            robot,
-            grid,
-            width,
-            height,
-            cell_width: 1,
-        }
-    }
+        map.robot = robot;
 
-    fn double_the_width(&mut self) {
-        // width of 1 -> 2
-        // everything duplicates except robot, which just moves
-        self.width *= 2;
-        self.cell_width = 2;
-        self.robot.1 *= 2;
+        map
+    }
 
-        let mut next_grid = HashMap::new();
+    fn add_box(&mut self, pos: (isize, isize), size: (isize, isize)) {
+        let id = self.boxes.len();
+        let b = Crate { pos, size };
 
-        for (&(r, c), &thing) in self.grid.iter() {
-            match thing {
-                Thing::Wall => {
-                    next_grid.insert((r, c * 2), thing);
-                    next_grid.insert((r, c * 2 + 1), thing);
-                }
-                Thing::Box => {
-                    next_grid.insert((r, c * 2), Thing::LBox);
-                    next_grid.insert((r, c * 2 + 1), Thing::RBox);
-                }
-                _ => {}
-            }
+        for cell in b.cells() {
+            self.grid.insert(cell, Thing::Box(id));
         }
 
-        self.grid = next_grid;
+        self.boxes.push(b);
     }
 
-    fn move_robot(&mut self, dir: (isize, isize)) {
-        let mut pos = self.robot;
+    /** stretches everything `n` times wider; except the robot, which just moves */
+    fn scale(&mut self, n: isize) {
+        self.width *= n as usize;
+        self.robot.1 *= n;
 
-        // look in the direction for ANY empty space before a wall
-        let mut found_boxes = false;
-        loop {
-            pos.0 += dir.0;
-            pos.1 += dir.1;
+        let boxes = std::mem::take(&mut self.boxes);
+        let walls = self.grid
+            .iter()
+            .filter(|(_, &thing)| thing == Thing::Wall)
+            .map(|(&pos, _)| pos)
+            .collect::<Vec<_>>();
 
-            match self.grid.get(&pos) {
-                None => {
-                    // can move
-                    break;
-                }
-                Some(Thing::Box) | Some(Thing::LBox) | Some(Thing::RBox) => {
-                    // might be able to move
-                    found_boxes = true;
-                    continue;
-                }
-                Some(Thing::Wall) => {
-                    // can't move
-                    return;
-                }
+        self.grid.clear();
+
+        for (r, c) in walls {
+            for i in 0..n {
+                self.grid.insert((r, c * n + i), Thing::Wall);
             }
         }
 
-        self.robot.0 += dir.0;
-        self.robot.1 += dir.1;
-
-        if !found_boxes {
-            return;
+        for b in boxes {
+            self.add_box((b.pos.0, b.pos.1 * n), (b.size.0, b.size.1 * n));
         }
+    }
 
-        if self.cell_width == 2 {
-            let mut cur = pos;
-
-            loop {
-                let next = tup!(cur - dir);
-
-                // let's move each thing as it is
-                let thing = self.grid.remove(&next).expect("Next to be box");
-
-                self.grid.insert(cur, thing);
-
-                if next == self.robot {
-                    // robot has pushed everything
-                    break;
-                }
-
-                cur = next;
-            }
-        } else {
-            // move first obstacle to position
-            // println!("moving {:?} -> {:?}", self.robot, pos);
-            self.grid.remove(&self.robot);
-            self.grid.insert(pos, Thing::Box);
+    /** applies one move; returns whether any boxes were pushed */
+    fn step(&mut self, m: char) -> bool {
+        match m {
+            '<' => self.move_robot((0, -1)),
+            '>' => self.move_robot((0, 1)),
+            '^' => self.move_robot((-1, 0)),
+            'v' => self.move_robot((1, 0)),
+            _ => false,
         }
     }
 
-    // dir is ONLY up or down; left or right can just use the `move_robot`
-    fn move_robot_2x_big(&mut self, dir: (isize, isize)) {
-        let pos = self.robot;
-
-        // check positions
-        let mut check_positions = vec![tup!(pos + dir)];
+    /**
+     * finds every box in the way, and every box in the way of those,
+     * whatever their size; any wall means nothing moves
+     */
+    fn move_robot(&mut self, dir: (isize, isize)) -> bool {
+        let mut check_positions = vec![tup!(self.robot, +, dir)];
+        let mut pushed: Vec<usize> = vec![];
 
-        let mut boxes = vec![];
         while let Some(peek) = check_positions.pop() {
             match self.grid.get(&peek) {
-                Some(Thing::LBox) => {
-                    boxes.push(peek);
-                    // check above/below and one to the right
-                    let next = tup!(peek + dir);
-                    check_positions.push(next);
-                    check_positions.push((next.0, next.1 + 1));
-                }
-                Some(Thing::RBox) => {
-                    // push left box
-                    boxes.push((peek.0, peek.1 - 1));
-                    // check above/below and one to the left
-                    let next = tup!(peek + dir);
-                    check_positions.push(next);
-                    check_positions.push((next.0, next.1 - 1));
+                Some(&Thing::Box(id)) => {
+                    if pushed.contains(&id) {
+                        continue;
+                    }
+                    pushed.push(id);
+
+                    // check each cell this box will move into
+                    for cell in self.boxes[id].cells() {
+                        let next = tup!(cell + dir);
+
+                        if self.grid.get(&next) != Some(&Thing::Box(id)) {
+                            check_positions.push(next);
+                        }
+                    }
                 }
                 Some(Thing::Wall) => {
                     // exit, can't move
-                    return;
+                    return false;
                 }
-                _ => {}
+                None => {}
             }
         }
 
         // let's move the robot and each box
-        self.robot.0 += dir.0;
-        self.robot.1 += dir.1;
+        self.robot = tup!(self.robot, +, dir);
 
         // remove all boxes
-        for b in boxes.iter() {
-            // remove left
-            self.grid.remove(b);
-            // remove right
-            self.grid.remove(&(b.0, b.1 + 1));
+        for &id in pushed.iter() {
+            for cell in self.boxes[id].cells() {
+                self.grid.remove(&cell);
+            }
         }
 
         // add all boxes one step in the direction
-        for b in boxes.iter() {
-            let n = tup!(b + dir);
-            self.grid.insert(n, Thing::LBox);
-            self.grid.insert((n.0, n.1 + 1), Thing::RBox);
+        for &id in pushed.iter() {
+            let b = &mut self.boxes[id];
+            b.pos = tup!(b.pos, +, dir);
+
+            for cell in b.cells() {
+                self.grid.insert(cell, Thing::Box(id));
+            }
         }
-    }
 
-    fn get_gps_coords(self) -> impl Iterator<Item = isize> {
-        // part 1 counts box; 2 counts LBox
-        let find = if self.cell_width == 1 { Thing::Box } else { Thing::LBox };
+        !pushed.is_empty()
+    }
 
-        // first `move`?
-        self.grid.into_iter().filter_map(move |((r, c), thing)| {
-            if thing == find {
-                return Some(r * 100 + c);
-            }
-            None
-        })
+    /** measured from each box's top-left corner */
+    fn get_gps_coords(&self) -> impl Iterator<Item = isize> + '_ {
+        self.boxes.iter().map(|b| b.pos.0 * 100 + b.pos.1)
     }
 }
 
@@ -213,17 +194,27 @@ impl Display for Map {
         for r in 0..self.height {
             let mut row: Vec<&str> = vec![];
             for c in 0..self.width {
-                if self.robot == (r as isize, c as isize) {
+                let pos = (r as isize, c as isize);
+
+                if self.robot == pos {
                     row.push("@");
-                } else {
-                    row.push(match self.grid.get(&(r as isize, c as isize)) {
-                        Some(Thing::Wall) => "#",
-                        Some(Thing::Box) => "O",
-                        Some(Thing::LBox) => "[",
-                        Some(Thing::RBox) => "]",
-                        _ => ".",
-                    });
+                    continue;
                 }
+                row.push(match self.grid.get(&pos) {
+                    Some(Thing::Wall) => "#",
+                    Some(&Thing::Box(id)) => {
+                        let b = self.boxes[id];
+                        let right = b.pos.1 + b.size.1 - 1;
+
+                        match pos.1 {
+                            _ if b.size.1 == 1 => "O",
+                            c if c == b.pos.1 => "[",
+                            c if c == right => "]",
+                            _ => "=",
+                        }
+                    }
+                    _ => ".",
+                });
             }
             out.push(row.join(""));
         }
@@ -231,6 +222,82 @@ impl Display for Map {
     }
 }
 
+/** the map after a move */
+struct Frame {
+    map: Map,
+    dir: char,
+    pushed: bool,
+}
+
+/** yields a `Frame` for every move, instead of applying them all in one go */
+struct Replay<'a> {
+    map: Map,
+    moves: std::str::Chars<'a>,
+}
+
+impl<'a> Replay<'a> {
+    fn new(map: &Map, moves: &'a str) -> Self {
+        Self { map: map.clone(), moves: moves.chars() }
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // skip the newlines
+        let dir = self.moves.find(|m| "<>^v".contains(*m))?;
+        let pushed = self.map.step(dir);
+
+        Some(Frame { map: self.map.clone(), dir, pushed })
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pushed = if self.pushed { " (pushed)" } else { "" };
+
+        write!(f, "Move {}{pushed}:\n{}", self.dir, self.map)
+    }
+}
+
+/** writes every frame to a file, or steps through them with `enter` */
+fn replay(map: &Map, moves: &str, interactive: bool) {
+    let frames = Replay::new(map, moves);
+
+    if !interactive {
+        let out = frames
+            .enumerate()
+            .map(|(i, frame)| format!("{i}. {frame}\n"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        fs::write("./src/replay.txt", out).unwrap();
+        println!("Wrote ./src/replay.txt");
+
+        return;
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    for (i, frame) in frames.enumerate() {
+        // clear the screen and go to the top left
+        print!("\x1b[2J\x1b[H{i}. {frame}\n(enter for next, q to quit) ");
+        io::stdout().flush().unwrap();
+
+        match lines.next() {
+            Some(Ok(line)) if line.trim() == "q" => {
+                break;
+            }
+            Some(Ok(_)) => {}
+            _ => {
+                break;
+            }
+        }
+    }
+}
+
 fn parse_data(data: &str) -> (Map, &str) {
     let (map, moves) = data.split_once("\n\n").expect("Space");
     let map = Map::new(map);
@@ -239,62 +306,22 @@ fn parse_data(data: &str) -> (Map, &str) {
 }
 
 fn part_one(map: &Map, moves: &str) -> isize {
-    let mut clone = Map {
-        // manual cloning because HashMap doesn't implement Copy
-        grid: HashMap::from_iter(map.grid.iter().map(|(&k, &v)| { (k, v) })),
-        ..*map
-    };
+    let mut clone = map.clone();
 
     for m in moves.chars() {
-        match m {
-            '<' => {
-                clone.move_robot((0, -1));
-            }
-            '^' => {
-                clone.move_robot((-1, 0));
-            }
-            '>' => {
-                clone.move_robot((0, 1));
-            }
-            'v' => {
-                clone.move_robot((1, 0));
-            }
-            _ => {}
-        }
-        // println!("move {m}");
-        // println!("{clone}");
+        clone.step(m);
     }
 
     clone.get_gps_coords().sum()
 }
 
 fn part_two(map: &Map, moves: &str) -> isize {
-    let mut clone = Map {
-        // manual cloning because HashMap doesn't implement Copy
-        grid: HashMap::from_iter(map.grid.iter().map(|(&k, &v)| { (k, v) })),
-        ..*map
-    };
+    let mut clone = map.clone();
 
-    clone.double_the_width();
+    clone.scale(2);
 
     for m in moves.chars() {
-        match m {
-            '<' => {
-                clone.move_robot((0, -1));
-            }
-            '^' => {
-                clone.move_robot_2x_big((-1, 0));
-            }
-            '>' => {
-                clone.move_robot((0, 1));
-            }
-            'v' => {
-                clone.move_robot_2x_big((1, 0));
-            }
-            _ => {}
-        }
-        // println!("move {m}");
-        // println!("{clone}");
+        clone.step(m);
     }
 
     clone.get_gps_coords().sum()
@@ -318,7 +345,21 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- replay wide step`, or `replay scale=3`
+    if has_flag("replay") {
+        let mut map = map;
+
+        // e.g. `wide` or `scale=3`
+        if has_flag("wide") {
+            map.scale(2);
+        } else if let Some(n) = flag_value("scale") {
+            map.scale(n);
+        }
+
+        replay(&map, moves, has_flag("step"));
+    }
 }
 
 #[cfg(test)]
@@ -380,6 +421,67 @@ mod tests {
         part_two(&map, moves);
     }
 
+    #[test]
+    fn test_replay() {
+        let (map, moves) = parse_data(
+            "#######
+#.O@..#
+#######
+
+<<<>"
+        );
+
+        let frames = Replay::new(&map, moves)
+            .map(|frame| (frame.dir, frame.pushed, frame.map.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(frames, vec![
+            ('<', true, "#######\n#O@...#\n#######".to_string()),
+            ('<', false, "#######\n#O@...#\n#######".to_string()),
+            ('<', false, "#######\n#O@...#\n#######".to_string()),
+            ('>', false, "#######\n#O.@..#\n#######".to_string())
+        ]);
+    }
+
+    #[test]
+    fn test_any_size() {
+        // a 2 wide box pushes a 3 wide box, which pushes a tall box
+        let mut map = Map::new("#######
+#.....#
+#.....#
+#.....#
+#[=]..#
+#..[].#
+#...@.#
+#######");
+        map.add_box((2, 3), (2, 1));
+
+        assert!(map.step('^'));
+        assert_eq!(map.to_string(), "#######
+#..O..#
+#..O..#
+#[=]..#
+#..[].#
+#...@.#
+#.....#
+#######");
+        assert_eq!(map.get_gps_coords().sum::<isize>(), 301 + 403 + 103);
+
+        // the wall stops the whole stack
+        assert!(!map.step('^'));
+        assert_eq!(map.robot, (5, 4));
+    }
+
+    #[test]
+    fn test_scale() {
+        let mut map = Map::new("#####\n#.O@#\n#####");
+        map.scale(3);
+
+        assert_eq!(map.to_string(), "###############\n###...[=]@..###\n###############");
+        assert!(map.step('<'));
+        assert_eq!(map.get_gps_coords().sum::<isize>(), 105);
+    }
+
     #[test]
     fn test_part_two() {
         let (map, moves) = parse_data(EXAMPLE);
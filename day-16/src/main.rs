@@ -1,5 +1,5 @@
-use std::{ collections::{ BinaryHeap, HashSet }, fs, time::Instant, usize };
-use lib::{ get_part, DIRS };
+use std::{ collections::{ BinaryHeap, HashSet }, fs, time::Instant };
+use lib::{ flag_value, get_part, has_flag, DIRS };
 
 struct Maze {
     start: isize,
@@ -177,95 +177,166 @@ fn part_one(maze: &Maze) -> usize {
     best
 }
 
-// isize -> i16 goes from ~450ms to ~270ms
-type FastestNum = i16;
+/** cost to step forward, and to turn 90° on the way */
+#[derive(Debug, Clone, Copy)]
+struct Costs {
+    step: usize,
+    turn: usize,
+}
 
-#[derive(PartialEq, Eq, Ord)]
-struct VisitedState {
-    current: (isize, usize),
+const DEFAULT_COSTS: Costs = Costs { step: 1, turn: 1000 };
+
+const WALL: u8 = b'#';
+
+/** every best path at once */
+struct Routes {
+    best: usize,
+    tiles: HashSet<isize>,
+    paths: u128,
+}
+
+/** min-heap entry over (cell, facing) */
+#[derive(PartialEq, Eq)]
+struct Entry {
     cost: usize,
-    visited: Vec<FastestNum>,
+    state: usize,
 }
 
-impl PartialOrd for VisitedState {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.cost.cmp(&self.cost))
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.cost.cmp(&self.cost)
     }
 }
 
-fn part_two(maze: &Maze) -> usize {
-    // let mut heap = vec![];
-    let mut heap = BinaryHeap::new();
-    let mut visited = HashSet::new();
-    let mut winners = HashSet::new();
-    let mut best = usize::MAX;
-
-    heap.push(VisitedState {
-        current: (maze.start, 1),
-        cost: 0,
-        visited: vec![maze.start as FastestNum],
-    });
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-    while let Some(state) = heap.pop() {
-        let (cell, dir) = state.current;
+impl Maze {
+    fn step(&self, cell: isize, dir: usize) -> isize {
+        let d = DIRS[dir];
 
-        // part 2 can prune if we've found better (can't omit visited cells)
-        if state.cost > best {
-            // drop anything if we already have better
-            continue;
-        }
+        if d.0 == 0 { cell + d.1 } else { cell + d.0 * (self.width as isize) }
+    }
 
-        // check if done
-        if cell == maze.end {
-            if state.cost < best {
-                // new winners
-                best = state.cost;
-                winners = HashSet::from_iter(state.visited.into_iter());
-            } else if state.cost == best {
-                for s in state.visited {
-                    winners.insert(s);
-                }
-            }
-            continue;
+    /**
+     * dijkstra over `cell * 4 + facing`, returning the cost of, and number of
+     * cheapest ways into, every state. Going `backwards` walks each move in reverse,
+     * so the costs are how far each state is *from* the sources
+     */
+    fn dijkstra(
+        &self,
+        sources: &[usize],
+        costs: Costs,
+        backwards: bool
+    ) -> (Vec<usize>, Vec<u128>) {
+        assert!(costs.step > 0, "can't count paths if steps are free");
+
+        let mut dist = vec![usize::MAX; self.cells.len() * 4];
+        let mut ways = vec![0u128; self.cells.len() * 4];
+        let mut heap = BinaryHeap::new();
+
+        for &state in sources {
+            dist[state] = 0;
+            ways[state] = 1;
+            heap.push(Entry { cost: 0, state });
         }
 
-        // get next states
-        for (i, d) in DIRS.iter().enumerate() {
-            // check only 3 directions
-            let ignore_dir = (dir + 2) % 4;
-
-            if i == ignore_dir {
+        while let Some(Entry { cost, state }) = heap.pop() {
+            if cost > dist[state] {
                 continue;
             }
-
-            // check if can move in direction
-            if let Some(next) = maze.move_from_cell(d, state.current.0) {
-                // remove if visited
-                if visited.contains(&(next, i)) {
+            let (cell, dir) = ((state / 4) as isize, state % 4);
+
+            // no turning around; check only 3 directions
+            for other in (0..4).filter(|&o| o != (dir + 2) % 4) {
+                // forwards: move from `cell` facing `other`.
+                // backwards: we got here facing `dir`, so came from behind, facing `other`
+                let (next, facing) = if backwards {
+                    (self.step(cell, (dir + 2) % 4), other)
+                } else {
+                    (self.step(cell, other), other)
+                };
+
+                if self.cells[next as usize] == WALL {
                     continue;
                 }
 
-                // check if direction change
-                let dir_cost = if i == dir { 0 } else { 1000 };
-                // update cost according to step + possible direction change
-
-                // visited.clone() -> with_capacity/extend improved from 275ms to 190ms
-                let mut next_visited = Vec::with_capacity(state.visited.len() + 1);
-                next_visited.extend_from_slice(&state.visited);
-                next_visited.push(next as FastestNum);
+                let turn = if other == dir { 0 } else { costs.turn };
+                let next_cost = cost + costs.step + turn;
+                let next_state = (next as usize) * 4 + facing;
 
-                heap.push(VisitedState {
-                    current: (next, i),
-                    cost: state.cost + 1 + dir_cost,
-                    visited: next_visited,
-                });
+                if next_cost < dist[next_state] {
+                    dist[next_state] = next_cost;
+                    ways[next_state] = ways[state];
+                    heap.push(Entry { cost: next_cost, state: next_state });
+                } else if next_cost == dist[next_state] {
+                    ways[next_state] = ways[next_state].saturating_add(ways[state]);
+                }
             }
         }
 
-        visited.insert(state.current);
+        (dist, ways)
+    }
+
+    /** a state is on a best path if the cost to get there and the cost to finish add up to best */
+    fn routes(&self, costs: Costs) -> Routes {
+        // start facing east
+        let start = (self.start as usize) * 4 + 1;
+        let ends = (0..4).map(|d| (self.end as usize) * 4 + d).collect::<Vec<_>>();
+
+        let (from_start, ways) = self.dijkstra(&[start], costs, false);
+        let (to_end, _) = self.dijkstra(&ends, costs, true);
+
+        let best = ends
+            .iter()
+            .map(|&e| from_start[e])
+            .min()
+            .unwrap();
+
+        let paths = ends
+            .iter()
+            .filter(|&&e| from_start[e] == best)
+            .fold(0u128, |acc, &e| acc.saturating_add(ways[e]));
+
+        let tiles = (0..from_start.len())
+            .filter(|&s| {
+                from_start[s] != usize::MAX &&
+                    to_end[s] != usize::MAX &&
+                    from_start[s] + to_end[s] == best
+            })
+            .map(|s| (s / 4) as isize)
+            .collect();
+
+        Routes { best, tiles, paths }
     }
 
-    winners.len()
+    /** the maze, with every best tile drawn as `O` */
+    fn render(&self, tiles: &HashSet<isize>) -> String {
+        self.cells
+            .chunks(self.width)
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(c, &cell)| {
+                        if tiles.contains(&((r * self.width + c) as isize)) {
+                            'O'
+                        } else {
+                            cell as char
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn part_two(maze: &Maze) -> usize {
+    maze.routes(DEFAULT_COSTS).tiles.len()
 }
 
 fn main() {
@@ -286,7 +357,22 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- routes turn=1 render`
+    if has_flag("routes") {
+        let costs = Costs {
+            step: flag_value("step").unwrap_or(DEFAULT_COSTS.step),
+            turn: flag_value("turn").unwrap_or(DEFAULT_COSTS.turn),
+        };
+        let routes = maze.routes(costs);
+
+        println!("Best: {} Tiles: {} Paths: {}", routes.best, routes.tiles.len(), routes.paths);
+
+        if has_flag("render") {
+            println!("{}", maze.render(&routes.tiles));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -308,4 +394,28 @@ mod tests {
 
         assert_eq!(ans, 45);
     }
+
+    #[test]
+    fn test_routes() {
+        let maze = Maze::new(EXAMPLE);
+        let routes = maze.routes(DEFAULT_COSTS);
+
+        assert_eq!(routes.best, 7036);
+        assert_eq!(routes.paths, 3);
+
+        let maze = Maze::new("#####\n#..E#\n#...#\n#S..#\n#####");
+
+        // right, right, up, up
+        let routes = maze.routes(DEFAULT_COSTS);
+        assert_eq!((routes.best, routes.paths, routes.tiles.len()), (1004, 1, 5));
+
+        // turning is free, so any way up and right
+        let routes = maze.routes(Costs { step: 1, turn: 0 });
+        assert_eq!((routes.best, routes.paths, routes.tiles.len()), (4, 6, 9));
+
+        assert_eq!(
+            maze.render(&maze.routes(DEFAULT_COSTS).tiles),
+            "#####\n#..O#\n#..O#\n#OOO#\n#####"
+        );
+    }
 }
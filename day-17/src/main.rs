@@ -1,4 +1,5 @@
 use std::{ fs, thread, time::Instant };
+use lib::has_flag;
 
 trait Instruction {
     fn run(program: &mut Program, operand: usize) -> ();
@@ -97,7 +98,7 @@ struct Bst {}
 
 impl Instruction for Bst {
     fn run(program: &mut Program, operand: usize) {
-        program.b = Combo::from(&program, operand) % 8;
+        program.b = Combo::from(program, operand) % 8;
     }
 }
 
@@ -109,7 +110,7 @@ struct Bxc {}
 
 impl Instruction for Bxc {
     fn run(program: &mut Program, _operand: usize) {
-        program.b = program.b ^ program.c;
+        program.b ^= program.c;
     }
 }
 
@@ -135,6 +136,83 @@ impl Instruction for Cdv {
     }
 }
 
+/**
+ * combo operand, resolved once when the program is decoded
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Literal(usize),
+    A,
+    B,
+    C,
+    // 7 is reserved; only panics if it's actually executed
+    Reserved,
+}
+
+impl Operand {
+    fn combo(value: usize) -> Self {
+        match value {
+            0..=3 => Operand::Literal(value),
+            4 => Operand::A,
+            5 => Operand::B,
+            6 => Operand::C,
+            _ => Operand::Reserved,
+        }
+    }
+
+    fn value(self, program: &Program) -> usize {
+        match self {
+            Operand::Literal(value) => value,
+            Operand::A => program.a,
+            Operand::B => program.b,
+            Operand::C => program.c,
+            Operand::Reserved => panic!("Why is this a combo? 7"),
+        }
+    }
+}
+
+/**
+ * same instructions as above, but as data instead of function pointers
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Adv(Operand),
+    Bxl(usize),
+    Bst(Operand),
+    Jnz(usize),
+    Bxc,
+    Out(Operand),
+    Bdv(Operand),
+    Cdv(Operand),
+}
+
+impl Op {
+    fn decode(inst: usize, operand: usize) -> Self {
+        match inst {
+            0 => Op::Adv(Operand::combo(operand)),
+            1 => Op::Bxl(operand),
+            2 => Op::Bst(Operand::combo(operand)),
+            3 => Op::Jnz(operand),
+            4 => Op::Bxc,
+            5 => Op::Out(Operand::combo(operand)),
+            6 => Op::Bdv(Operand::combo(operand)),
+            7 => Op::Cdv(Operand::combo(operand)),
+            _ => panic!("what instruction is this? {inst}"),
+        }
+    }
+}
+
+/**
+ * decodes an instruction at *every* offset, not just the even ones,
+ * so that a jump to an odd pointer still behaves like the original
+ */
+fn decode(input: &[usize]) -> Vec<Op> {
+    input
+        .windows(2)
+        .map(|w| Op::decode(w[0], w[1]))
+        .collect()
+}
+
 #[derive(Debug)]
 struct Program {
     a: usize,
@@ -143,14 +221,15 @@ struct Program {
     pointer: usize,
     input: Vec<usize>,
     output: Vec<usize>,
+    ops: Vec<Op>,
 }
 
 impl Program {
     // this is so awkward
     fn new(data: &str) -> Self {
         let mut lines = data.lines().filter_map(|l| {
-            // needs and_then for the empty line
-            l.split_once(": ").and_then(|x| Some(x.1))
+            // returns None for the empty line
+            l.split_once(": ").map(|x| x.1)
         });
 
         let a = lines.next().unwrap().parse().unwrap();
@@ -162,7 +241,9 @@ impl Program {
             .unwrap()
             .split(",")
             .filter_map(|d| d.parse().ok())
-            .collect();
+            .collect::<Vec<_>>();
+
+        let ops = decode(&input);
 
         Self {
             a,
@@ -171,10 +252,60 @@ impl Program {
             pointer: 0,
             input,
             output: vec![],
+            ops,
         }
     }
 
+    /** pre-decoded interpreter */
     fn run(&mut self) -> Option<()> {
+        loop {
+            // Op is Copy, so this doesn't hold a borrow on self
+            let op = *self.ops.get(self.pointer)?;
+
+            self.pointer += 2;
+
+            match op {
+                Op::Adv(operand) => {
+                    self.a = self.div(operand);
+                }
+                Op::Bxl(literal) => {
+                    self.b ^= literal;
+                }
+                Op::Bst(operand) => {
+                    self.b = operand.value(self) % 8;
+                }
+                Op::Jnz(literal) => {
+                    if self.a != 0 {
+                        self.pointer = literal;
+                    }
+                }
+                Op::Bxc => {
+                    self.b ^= self.c;
+                }
+                Op::Out(operand) => {
+                    let value = operand.value(self) % 8;
+                    self.output.push(value);
+                }
+                Op::Bdv(operand) => {
+                    self.b = self.div(operand);
+                }
+                Op::Cdv(operand) => {
+                    self.c = self.div(operand);
+                }
+            }
+        }
+    }
+
+    fn div(&self, operand: Operand) -> usize {
+        // same as a / 2^combo
+        u32::try_from(operand.value(self))
+            .ok()
+            .and_then(|shift| self.a.checked_shr(shift))
+            .unwrap_or(0)
+    }
+
+    /** original interpreter, decodes every step; kept for the bench */
+    fn run_legacy(&mut self) -> Option<()> {
         loop {
             // first time using question marks
             let inst: &usize = self.input.get(self.pointer)?;
@@ -219,6 +350,11 @@ fn part1(program: &mut Program) -> String {
 }
 
 fn part2(program: &mut Program) -> usize {
+    search(program, Program::run)
+}
+
+/** part2, but with either interpreter */
+fn search(program: &mut Program, run: fn(&mut Program) -> Option<()>) -> usize {
     let mut queue = vec![];
 
     queue.push((0, program.input.len() - 1));
@@ -233,7 +369,7 @@ fn part2(program: &mut Program) -> usize {
 
             program.reset();
             program.a = next_a;
-            program.run();
+            run(program);
 
             // println!("{next_a} {:?}", program.output);
 
@@ -271,6 +407,29 @@ fn main() {
     println!("Part two: {:?} {:?}", ans, now.elapsed());
 
     println!("Time: {:?}", start.elapsed());
+
+    if has_flag("bench") {
+        bench(&mut program);
+    }
+}
+
+/** runs the same part2 search through both interpreters */
+fn bench(program: &mut Program) {
+    program.reset();
+    let now = Instant::now();
+    let legacy = search(program, Program::run_legacy);
+    let legacy_time = now.elapsed();
+
+    program.reset();
+    let now = Instant::now();
+    let decoded = search(program, Program::run);
+    let decoded_time = now.elapsed();
+
+    assert_eq!(legacy, decoded, "interpreters disagree");
+
+    println!("Legacy:  {:?} {:?}", legacy, legacy_time);
+    println!("Decoded: {:?} {:?}", decoded, decoded_time);
+    println!("Speedup: {:.2}x", legacy_time.as_secs_f64() / decoded_time.as_secs_f64());
 }
 
 fn _crazy_idea_i_had() {
@@ -292,3 +451,44 @@ fn _crazy_idea_i_had() {
         handle.join().unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("./example.txt");
+
+    #[test]
+    fn test_part_one() {
+        let mut program = Program::new(EXAMPLE);
+
+        assert_eq!(part1(&mut program), "4,6,3,5,6,3,5,2,1,0");
+    }
+
+    #[test]
+    fn test_decoded_matches_legacy() {
+        let mut program = Program::new(
+            "Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 2,4,1,1,7,5,4,4,1,4,0,3,5,5,3,0"
+        );
+
+        for a in 0..4096 {
+            program.reset();
+            program.a = a;
+            program.run_legacy();
+            let expected = program.output.clone();
+
+            program.reset();
+            program.a = a;
+            program.run();
+
+            assert_eq!(program.output, expected, "a = {a}");
+        }
+    }
+
+    #[test]
+    fn test_decode_odd_offsets() {
+        let ops = decode(&[0, 1, 5, 4]);
+
+        assert_eq!(ops, vec![Op::Adv(Operand::Literal(1)), Op::Bxl(5), Op::Out(Operand::A)]);
+    }
+}
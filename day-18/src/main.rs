@@ -1,5 +1,5 @@
-use std::{ collections::{ BinaryHeap, HashSet }, fs, time::Instant };
-use lib::{ get_part, tup, DIRS };
+use std::{ collections::{ BinaryHeap, HashSet, VecDeque }, fs, time::Instant };
+use lib::{ flag_value, get_part, has_flag, tup, DIRS };
 
 struct Maze {
     //misnomer for end
@@ -66,79 +66,174 @@ fn part_one(maze: &Maze, fallen: usize) -> usize {
     steps
 }
 
-fn part_two(maze: &Maze, start: usize) -> String {
-    // repeat part one until we get '0'
-    // for i in start.. {
-    //     if part_one(maze, i) == 0 {
-    //         let pos = maze.bytes[i - 1];
+/** disjoint sets of open cells */
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
 
-    //         println!("{i}");
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        Self { parent: (0..n).collect(), size: vec![1; n] }
+    }
 
-    //         return format!("{},{}", pos.0, pos.1);
-    //     }
-    // }
-    // "".to_string()
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            // path halving
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
 
-    let mut obstacles: HashSet<&(isize, isize)> = HashSet::from_iter(maze.bytes.iter().take(start));
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
 
-    let end = (maze.size, maze.size);
-    let bounds = 0..=maze.size;
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            (a, b) = (b, a);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
 
-    // moving this outside and using .clear() saved 100ms
-    let mut visited = HashSet::new();
-    let mut heap = BinaryHeap::new();
+impl Maze {
+    fn side(&self) -> usize {
+        (self.size + 1) as usize
+    }
 
-    // each iteration adds a new obstacle
-    // we should be able to check if a new obstacle hit our path
-    'throwingsnow: for i in start..maze.bytes.len() {
-        let obs = &maze.bytes[i];
+    /** index of the byte that lands on each cell; usize::MAX if none ever does */
+    fn fall_times(&self) -> Vec<usize> {
+        let side = self.side();
+        let mut times = vec![usize::MAX; side * side];
 
-        obstacles.insert(obs);
+        for (i, &(x, y)) in self.bytes.iter().enumerate() {
+            let cell = (y as usize) * side + (x as usize);
+            // only the first one counts
+            times[cell] = times[cell].min(i);
+        }
 
-        visited.clear();
-        heap.clear();
+        times
+    }
 
-        // maybe should start somewhere else?
-        heap.push((0, (0, 0)));
+    fn neighbours(&self, cell: usize) -> impl Iterator<Item = usize> {
+        let side = self.side() as isize;
+        let pos = ((cell as isize) / side, (cell as isize) % side);
 
-        while let Some((cost, position)) = heap.pop() {
-            if visited.contains(&position) {
-                continue;
-            }
+        DIRS.into_iter().filter_map(move |dir| {
+            let (r, c) = tup!(pos, +, dir);
 
-            if position == end {
-                continue 'throwingsnow;
+            if r < 0 || c < 0 || r >= side || c >= side {
+                return None;
             }
+            Some((r * side + c) as usize)
+        })
+    }
+
+    /** bfs from the top left, after the first `fallen` bytes */
+    fn reachable(&self, times: &[usize], fallen: usize) -> bool {
+        let end = times.len() - 1;
+        let mut seen = vec![false; times.len()];
+        let mut queue = VecDeque::from([0]);
+
+        if times[0] < fallen {
+            return false;
+        }
+        seen[0] = true;
 
-            // println!("{:?}", state);
-            // get next states
-            for dir in DIRS {
-                // needs commas
-                let next = tup!(position + dir);
-
-                // check bounds & obsacles
-                if
-                    !(
-                        bounds.contains(&next.0) &&
-                        bounds.contains(&next.1) &&
-                        !obstacles.contains(&next)
-                    )
-                {
-                    continue;
+        while let Some(cell) = queue.pop_front() {
+            if cell == end {
+                return true;
+            }
+            for next in self.neighbours(cell) {
+                if !seen[next] && times[next] >= fallen {
+                    seen[next] = true;
+                    queue.push_back(next);
                 }
+            }
+        }
 
-                heap.push((cost + 1, next));
+        false
+    }
+}
+
+/**
+ * drop every byte, then take them away again last to first, joining up the
+ * open cells as we go: the byte that connects start and end is the blocker.
+ * The first `fallen` bytes are assumed not to block the exit
+ */
+fn first_blocker_union_find(maze: &Maze, fallen: usize) -> Option<(isize, isize)> {
+    let times = maze.fall_times();
+    let end = times.len() - 1;
+    let mut sets = UnionFind::new(times.len());
+
+    let open = |sets: &mut UnionFind, cell: usize, before: usize| {
+        for next in maze.neighbours(cell) {
+            if times[next] >= before {
+                sets.union(cell, next);
             }
+        }
+    };
 
-            visited.insert(position);
+    // cells nothing ever lands on
+    for cell in (0..times.len()).filter(|&c| times[c] == usize::MAX) {
+        open(&mut sets, cell, usize::MAX);
+    }
+
+    // still a way out with everything fallen
+    if sets.find(0) == sets.find(end) {
+        return None;
+    }
+
+    for i in (fallen..maze.bytes.len()).rev() {
+        let (x, y) = maze.bytes[i];
+        let cell = (y as usize) * maze.side() + (x as usize);
+
+        // a repeat of a byte that already fell
+        if times[cell] != i {
+            continue;
         }
 
-        println!("{i}");
+        open(&mut sets, cell, i);
 
-        return format!("{},{}", obs.0, obs.1);
+        if sets.find(0) == sets.find(end) {
+            return Some(maze.bytes[i]);
+        }
     }
 
-    return "FAIL".to_string();
+    None
+}
+
+/** the smallest number of fallen bytes with no way out; its last byte is the blocker */
+fn first_blocker_binary_search(maze: &Maze, fallen: usize) -> Option<(isize, isize)> {
+    let times = maze.fall_times();
+    let (mut lo, mut hi) = (fallen, maze.bytes.len());
+
+    if maze.reachable(&times, hi) {
+        return None;
+    }
+
+    // reachable with `lo` bytes, not with `hi`
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+
+        if maze.reachable(&times, mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Some(maze.bytes[hi - 1])
+}
+
+fn part_two(maze: &Maze, fallen: usize) -> String {
+    first_blocker_union_find(maze, fallen)
+        .map(|pos| format!("{},{}", pos.0, pos.1))
+        .unwrap_or("FAIL".to_string())
 }
 
 fn main() {
@@ -146,21 +241,33 @@ fn main() {
     let start = Instant::now();
     let data = fs::read_to_string("./src/input.txt").unwrap();
 
-    let maze = Maze::new(&data, 70);
+    // the example is `size=6 fallen=12`
+    let maze = Maze::new(&data, flag_value("size").unwrap_or(70));
+    let fallen = flag_value("fallen").unwrap_or(1024);
 
     if one {
         let now = Instant::now();
-        let ans = part_one(&maze, 1024);
+        let ans = part_one(&maze, fallen);
         println!("Part one: {:?} {:?}", ans, now.elapsed());
     }
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&maze, 1025);
+        let ans = part_two(&maze, fallen);
         println!("Part two: {} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    if has_flag("bench") {
+        let now = Instant::now();
+        let ans = first_blocker_union_find(&maze, fallen);
+        println!("Union-find:    {:?} {:?}", ans, now.elapsed());
+
+        let now = Instant::now();
+        let ans = first_blocker_binary_search(&maze, fallen);
+        println!("Binary search: {:?} {:?}", ans, now.elapsed());
+    }
 }
 
 #[cfg(test)]
@@ -182,4 +289,18 @@ mod tests {
 
         assert_eq!(ans, "6,1");
     }
+
+    #[test]
+    fn test_blockers() {
+        let maze = Maze::new(EXAMPLE, 6);
+
+        assert_eq!(first_blocker_union_find(&maze, 12), Some((6, 1)));
+        assert_eq!(first_blocker_binary_search(&maze, 12), Some((6, 1)));
+
+        // never blocked
+        let maze = Maze::new("1,1\n2,2", 6);
+
+        assert_eq!(first_blocker_union_find(&maze, 0), None);
+        assert_eq!(first_blocker_binary_search(&maze, 0), None);
+    }
 }
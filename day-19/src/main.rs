@@ -1,125 +1,137 @@
-use std::{ collections::{ BinaryHeap, HashMap, HashSet }, fs, time::Instant, vec };
-use lib::get_part;
+use std::{ fs, time::Instant, vec };
+use lib::{ get_part, has_flag };
+
+const COLORS: &[u8] = b"wubrg";
+
+/** one node per prefix of an available pattern; `towel` marks a whole pattern */
+#[derive(Debug, Default, Clone)]
+struct Node {
+    // 0 is the root, which is never anyone's child
+    children: [usize; COLORS.len()],
+    towel: bool,
+}
 
 #[derive(Debug)]
 struct Towels<'a> {
-    available: HashSet<&'a str>,
+    trie: Vec<Node>,
     desired: Vec<&'a str>,
-    largest: usize,
 }
 
 impl<'a> Towels<'a> {
     fn new(data: &'a str) -> Self {
         let (a, d) = data.split_once("\n\n").expect("empty line");
 
-        let available = HashSet::from_iter(a.split(", "));
-        let desired = d.lines().collect();
+        let mut trie = vec![Node::default()];
 
-        let largest = available
-            .iter()
-            .map(|x| x.len())
-            .max()
-            .unwrap();
+        for pattern in a.split(", ") {
+            let mut node = 0;
 
-        Self { available, desired, largest }
-    }
+            for stripe in pattern.bytes() {
+                let color = COLORS.iter()
+                    .position(|&c| c == stripe)
+                    .expect("a known color");
 
-    fn dfs(&self, search: &'a str, memo: &mut HashMap<&'a str, usize>) -> usize {
-        if let Some(&count) = memo.get(search) {
-            return count;
-        }
-        let mut count = 0;
-
-        // cuts about half the time: 35ms to 12ms
-        let len = search.len().min(self.largest);
-
-        // get a range for the string slice
-        for e in 1..=len {
-            // find a pattern
-            if self.available.contains(&search[..e]) {
-                // if we're at the end, then this is 1 pattern,
-                // otherwise, search the rest of the string
-                count += if e == search.len() { 1 } else { self.dfs(&search[e..], memo) };
+                if trie[node].children[color] == 0 {
+                    trie.push(Node::default());
+                    trie[node].children[color] = trie.len() - 1;
+                }
+                node = trie[node].children[color];
             }
+
+            trie[node].towel = true;
         }
 
-        memo.insert(search, count);
+        let desired = d.lines().collect();
 
-        count
+        Self { trie, desired }
     }
 
-    fn possible(&self, desired: &str) -> usize {
-        self.dfs(desired, &mut HashMap::new())
+    /** every end position of a towel that fits `design` from `start` */
+    fn matches<'b>(&'b self, design: &'b [u8], start: usize) -> impl Iterator<Item = usize> + 'b {
+        let mut node = 0;
+
+        (start..design.len()).map_while(move |i| {
+            let color = COLORS.iter().position(|&c| c == design[i])?;
+
+            node = self.trie[node].children[color];
+
+            if node == 0 { None } else { Some((i + 1, self.trie[node].towel)) }
+        })
+            .filter_map(|(end, towel)| towel.then_some(end))
     }
-}
 
-#[derive(PartialEq, Eq)]
-struct Range {
-    start: usize,
-    end: usize,
-}
+    /** ways[i] is how many ways there are to make `design[i..]`, from the back */
+    fn ways(&self, design: &str) -> Vec<u128> {
+        let design = design.as_bytes();
+        let mut ways = vec![0; design.len() + 1];
+
+        ways[design.len()] = 1;
+
+        for start in (0..design.len()).rev() {
+            ways[start] = self
+                .matches(design, start)
+                .map(|end| ways[end])
+                .sum();
+        }
 
-impl Ord for Range {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // other.cmp(self) is min-heap
-        // self.cmp(other) is max-heap
-        self.start.cmp(&other.start)
+        ways
     }
-}
 
-impl PartialOrd for Range {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    fn possible(&self, design: &str) -> u128 {
+        self.ways(design)[0]
     }
-}
 
-fn can_make_towel(towels: &Towels, desired: &str) -> bool {
-    // start with window 1
-    // expand and iterate back at window 1
-    // (index, chunk)
-    let mut visited = vec![false; desired.len()];
-    let mut heap = BinaryHeap::new();
-
-    heap.push(Range {
-        start: 0,
-        end: 1,
-    });
-
-    while let Some(range) = heap.pop() {
-        if range.start == desired.len() {
-            return true;
-        }
+    /** one way to make the design, towel by towel */
+    fn decompose(&self, design: &'a str) -> Option<Vec<&'a str>> {
+        let ways = self.ways(design);
+        let mut towels = vec![];
+        let mut start = 0;
 
-        if visited[range.start] {
-            continue;
+        if ways[0] == 0 {
+            return None;
         }
 
-        // get next states
-        for end in range.end..=desired.len() {
-            let chunk = &desired[range.start..end];
+        while start < design.len() {
+            // any towel that leaves something makeable
+            let end = self
+                .matches(design.as_bytes(), start)
+                .find(|&end| ways[end] > 0)
+                .unwrap();
 
-            if towels.available.contains(&chunk) {
-                heap.push(Range {
-                    start: end,
-                    end: end + 1,
-                });
-            }
+            towels.push(&design[start..end]);
+            start = end;
         }
 
-        visited[range.start] = true;
+        Some(towels)
     }
 
-    false
+    /** the fewest towels that make the design */
+    fn fewest(&self, design: &str) -> Option<usize> {
+        let bytes = design.as_bytes();
+        let mut fewest = vec![None; bytes.len() + 1];
+
+        fewest[bytes.len()] = Some(0);
+
+        for start in (0..bytes.len()).rev() {
+            fewest[start] = self
+                .matches(bytes, start)
+                .filter_map(|end| fewest[end])
+                .min()
+                .map(|n: usize| n + 1);
+        }
+
+        fewest[0]
+    }
 }
 
 fn part_one(towels: &Towels) -> usize {
     towels.desired
         .iter()
-        .filter(|d| can_make_towel(towels, d))
+        .filter(|d| towels.possible(d) > 0)
         .count()
 }
 
-fn part_two(towels: &Towels) -> usize {
+fn part_two(towels: &Towels) -> u128 {
     towels.desired
         .iter()
         .map(|d| towels.possible(d))
@@ -144,7 +156,18 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    if has_flag("towels") {
+        for design in towels.desired.iter() {
+            match (towels.decompose(design), towels.fewest(design)) {
+                (Some(parts), Some(fewest)) => {
+                    println!("{design}: {} (fewest {fewest})", parts.join(","));
+                }
+                _ => println!("{design}: impossible"),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -166,4 +189,36 @@ mod tests {
 
         assert_eq!(ans, 16);
     }
+
+    #[test]
+    fn test_possible() {
+        let towels = Towels::new(EXAMPLE);
+        let counts = towels.desired
+            .iter()
+            .map(|d| towels.possible(d))
+            .collect::<Vec<_>>();
+
+        assert_eq!(counts, [2, 1, 4, 6, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_decompose() {
+        let towels = Towels::new(EXAMPLE);
+
+        assert_eq!(towels.decompose("rrbgbr"), Some(vec!["r", "r", "b", "g", "b", "r"]));
+        assert_eq!(towels.decompose("ubwu"), None);
+        assert_eq!(towels.fewest("rrbgbr"), Some(4));
+        assert_eq!(towels.fewest("bwurrg"), Some(4));
+        assert_eq!(towels.fewest("gbbr"), Some(2));
+        assert_eq!(towels.fewest("bbrgwb"), None);
+    }
+
+    #[test]
+    fn test_huge_count() {
+        // fibonacci many ways, which overflows a usize
+        let data = format!("w, ww\n\n{}", "w".repeat(150));
+        let towels = Towels::new(&data);
+
+        assert!(towels.possible(towels.desired[0]) > (usize::MAX as u128));
+    }
 }
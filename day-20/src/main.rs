@@ -1,5 +1,5 @@
-use std::{ collections::HashMap, fs, time::Instant, vec };
-use lib::{ get_part, DIRS, tup };
+use std::{ collections::{ BTreeMap, VecDeque }, fs, time::Instant, vec };
+use lib::{ flag_value, get_part };
 
 struct Race {
     start: isize,
@@ -9,8 +9,7 @@ struct Race {
     width: usize,
 }
 
-const SPACE: u8 = b'.';
-const END: u8 = b'E';
+const WALL: u8 = b'#';
 
 impl Race {
     // copied from day 16
@@ -42,96 +41,6 @@ impl Race {
         Self { start, end, cells, width }
     }
 
-    // had this so wrong with something like (-3, 15) which should have been
-    // off the map, if not for my previous logic
-    fn move_from_cell(&self, dir: &(isize, isize), cell: isize) -> Option<isize> {
-        // can't figure out the math, so converting to point
-        let p = (cell / (self.width as isize), cell % (self.width as isize));
-
-        let next = tup!(p + dir);
-
-        if next.0 < 1 || next.1 < 1 || next.1 >= (self.width as isize) {
-            return None;
-        }
-
-        // point back to cell index
-        let next = next.0 * (self.width as isize) + next.1;
-
-        // usng .get because *2 could overflow in any direction
-        match self.cells.get(next as usize) {
-            Some(&SPACE) | Some(&END) => { Some(next) }
-            _ => { None }
-        }
-    }
-
-    // this functions is a bit faster: 800us vs 1.2ms
-    fn _run(&self) -> HashMap<isize, isize> {
-        // do the race, keep track of every point you could cheat
-        // keep track of every cell's time
-        let mut times = vec![-1; self.cells.len()];
-        // (to -> from)
-        let mut cheats = vec![];
-        let mut current = self.start;
-
-        let double = (2, 2);
-        let mut steps = 0;
-
-        while current != self.end {
-            // visited
-            times[current as usize] = steps;
-            steps += 1;
-
-            let mut next = current;
-
-            // move through the single-path track
-            for dir in DIRS {
-                let check = self.move_from_cell(&dir, current);
-
-                if let Some(val) = check {
-                    // no cheat in this direction
-                    // check if visited
-                    // otherwise ignore
-                    if times[val as usize] == -1 {
-                        next = val;
-                    }
-                } else {
-                    // try to cheat (through 1 wall)
-                    if let Some(cheat) = self.move_from_cell(&tup!(dir * double), current) {
-                        // check if going backwards
-                        if times[cheat as usize] == -1 {
-                            cheats.push((cheat, current));
-                        }
-                    }
-                }
-            }
-
-            // update next loop
-            current = next;
-        }
-
-        times[self.end as usize] = steps;
-
-        let mut savings = HashMap::new();
-
-        for cheat in cheats {
-            let to = times[cheat.0 as usize];
-            let from = times[cheat.1 as usize];
-
-            // minus 2 for the steps through the wall
-            let diff = to - from - 2;
-
-            savings
-                .entry(diff)
-                .and_modify(|x| {
-                    *x += 1;
-                })
-                .or_insert(1);
-        }
-
-        // all cheats should have costs and point to other cells with costs
-        savings
-    }
-
     fn _print(&self, cell: isize, marker: &str) {
         // converts cell index to (r,c)
         let find = ((cell as usize) / self.width, (cell as usize) % self.width);
@@ -153,107 +62,130 @@ impl Race {
         }
     }
 
-    fn run_a_second_time(&self, cheat_dist: isize) -> HashMap<isize, isize> {
-        // do the race, keep track of every point you could cheat
-        // keep track of every cell's time
-        let mut times = vec![-1; self.cells.len()];
-        // (to -> from -> steps)
-        let mut cheats = vec![];
+    /** bfs steps from `from` to every cell; None for walls or unreachable */
+    fn distances(&self, from: isize) -> Vec<Option<usize>> {
+        let mut dist = vec![None; self.cells.len()];
+        let mut queue = VecDeque::from([from as usize]);
 
-        let mut current = self.start;
-        let mut steps = 0;
+        dist[from as usize] = Some(0);
 
-        // get all direction diffs
-        let mut manhattans = vec![];
+        while let Some(cell) = queue.pop_front() {
+            let steps = dist[cell].unwrap();
 
-        // TODO: had these wrong, didn't abs them in nested checks
-        for i in -cheat_dist..=cheat_dist {
-            for j in -cheat_dist..=cheat_dist {
-                if i.abs() + j.abs() <= cheat_dist {
-                    // ignore adjacent walls with <2
-                    if !(i.abs() < 2 && j.abs() < 2) {
-                        manhattans.push((i, j));
-                    }
+            for next in self.neighbours(cell, 1) {
+                if dist[next.0].is_none() && self.cells[next.0] != WALL {
+                    dist[next.0] = Some(steps + 1);
+                    queue.push_back(next.0);
                 }
             }
         }
 
-        while current != self.end {
-            // visited check
-            times[current as usize] = steps;
-            steps += 1;
-
-            // move through the single-path track
-            let next = DIRS.iter()
-                .find_map(|dir| {
-                    self.move_from_cell(&dir, current).filter(|&val| {
-                        // no cheat in this direction
-                        // check if unvisited
-                        // otherwise ignore
-                        times[val as usize] == -1
-                    })
-                })
-                .unwrap();
-
-            // try to cheat (through X number of walls)
-            for dir in manhattans.iter() {
-                if let Some(cheat) = self.move_from_cell(&dir, current) {
-                    // check if visited!
-                    if times[cheat as usize] == -1 {
-                        cheats.push((cheat, current, dir.0.abs() + dir.1.abs()));
-                    }
-                }
-            }
+        dist
+    }
 
-            // update next loop
-            current = next;
-        }
+    /** every in-bounds cell within `reach` steps (manhattan) of `cell`, and how far it is */
+    fn neighbours(&self, cell: usize, reach: isize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width as isize;
+        let height = (self.cells.len() / self.width) as isize;
+        let (r, c) = ((cell as isize) / width, (cell as isize) % width);
 
-        times[self.end as usize] = steps;
+        (-reach..=reach).flat_map(move |dr| {
+            let left = reach - dr.abs();
 
-        let mut savings = HashMap::new();
+            (-left..=left).filter_map(move |dc| {
+                let (nr, nc) = (r + dr, c + dc);
+                let dist = (dr.abs() + dc.abs()) as usize;
 
-        for cheat in cheats {
-            let cheat_time = times[cheat.0 as usize];
-            let race_time = times[cheat.1 as usize];
+                if dist == 0 || nr < 0 || nc < 0 || nr >= height || nc >= width {
+                    return None;
+                }
+                Some(((nr * width + nc) as usize, dist))
+            })
+        })
+    }
+
+    /**
+     * every cheat of up to `duration` steps that saves at least `at_least`;
+     * uses distances from both ends, so the track doesn't need to be a single corridor
+     */
+    fn cheats(&self, duration: usize, at_least: usize) -> Vec<Cheat> {
+        let from_start = self.distances(self.start);
+        let to_end = self.distances(self.end);
+        let baseline = from_start[self.end as usize].expect("a way to the end");
+        let to_point = |cell: usize| (cell / self.width, cell % self.width);
+
+        let mut cheats = vec![];
 
-            // cheat time - non-cheat time - steps through the wall
-            let diff = cheat_time - race_time - cheat.2;
+        for (a, before) in from_start.iter().enumerate() {
+            let Some(before) = before else {
+                continue;
+            };
 
-            savings
-                .entry(diff)
-                .and_modify(|x| {
-                    *x += 1;
-                })
-                .or_insert(1);
+            for (b, dist) in self.neighbours(a, duration as isize) {
+                // a single step can't go through a wall
+                if dist < 2 {
+                    continue;
+                }
+                let Some(after) = to_end[b] else {
+                    continue;
+                };
+
+                let time = before + dist + after;
+
+                if time < baseline && baseline - time >= at_least {
+                    cheats.push(Cheat {
+                        start: to_point(a),
+                        end: to_point(b),
+                        saving: baseline - time,
+                    });
+                }
+            }
         }
 
-        savings
+        cheats
     }
 }
 
-fn part_one(race: &Race, at_least: usize) -> usize {
-    race.run_a_second_time(2)
+/** a shortcut from `start` to `end` (row, col), and how much time it saves */
+#[derive(Debug, PartialEq)]
+struct Cheat {
+    start: (usize, usize),
+    end: (usize, usize),
+    saving: usize,
+}
+
+/** saving -> number of cheats */
+fn histogram(cheats: &[Cheat]) -> BTreeMap<usize, usize> {
+    let mut counts = BTreeMap::new();
+
+    for cheat in cheats {
+        *counts.entry(cheat.saving).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/** the histogram, worded just like the puzzle */
+fn report(cheats: &[Cheat]) -> String {
+    histogram(cheats)
         .iter()
-        .filter_map(|(&seconds, &count)| {
-            if (seconds as usize) >= at_least {
-                return Some(count as usize);
+        .map(|(saving, &count)| {
+            if count == 1 {
+                format!("There is one cheat that saves {saving} picoseconds.")
+            } else {
+                format!("There are {count} cheats that save {saving} picoseconds.")
             }
-            None
         })
-        .sum()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn part_one(race: &Race, at_least: usize) -> usize {
+    race.cheats(2, at_least).len()
 }
 
 fn part_two(race: &Race, at_least: usize) -> usize {
-    race.run_a_second_time(20)
-        .iter()
-        .filter_map(|(&seconds, &count)| {
-            if (seconds as usize) >= at_least {
-                return Some(count as usize);
-            }
-            None
-        })
-        .sum()
+    race.cheats(20, at_least).len()
 }
 
 fn main() {
@@ -274,7 +206,12 @@ fn main() {
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- cheat=20 save=50`
+    if let Some(duration) = flag_value("cheat") {
+        println!("{}", report(&race.cheats(duration, flag_value("save").unwrap_or(1))));
+    }
 }
 
 #[cfg(test)]
@@ -296,4 +233,63 @@ mod tests {
 
         assert_eq!(ans, 285);
     }
+
+    #[test]
+    fn test_report_one() {
+        let cheats = Race::new(EXAMPLE).cheats(2, 1);
+
+        assert_eq!(
+            report(&cheats),
+            "There are 14 cheats that save 2 picoseconds.
+There are 14 cheats that save 4 picoseconds.
+There are 2 cheats that save 6 picoseconds.
+There are 4 cheats that save 8 picoseconds.
+There are 2 cheats that save 10 picoseconds.
+There are 3 cheats that save 12 picoseconds.
+There is one cheat that saves 20 picoseconds.
+There is one cheat that saves 36 picoseconds.
+There is one cheat that saves 38 picoseconds.
+There is one cheat that saves 40 picoseconds.
+There is one cheat that saves 64 picoseconds."
+        );
+        assert!(cheats.contains(&Cheat { start: (7, 7), end: (7, 5), saving: 64 }));
+    }
+
+    #[test]
+    fn test_report_two() {
+        let cheats = Race::new(EXAMPLE).cheats(20, 50);
+
+        assert_eq!(
+            report(&cheats),
+            "There are 32 cheats that save 50 picoseconds.
+There are 31 cheats that save 52 picoseconds.
+There are 29 cheats that save 54 picoseconds.
+There are 39 cheats that save 56 picoseconds.
+There are 25 cheats that save 58 picoseconds.
+There are 23 cheats that save 60 picoseconds.
+There are 20 cheats that save 62 picoseconds.
+There are 19 cheats that save 64 picoseconds.
+There are 12 cheats that save 66 picoseconds.
+There are 14 cheats that save 68 picoseconds.
+There are 12 cheats that save 70 picoseconds.
+There are 22 cheats that save 72 picoseconds.
+There are 4 cheats that save 74 picoseconds.
+There are 3 cheats that save 76 picoseconds."
+        );
+    }
+
+    #[test]
+    fn test_open_track() {
+        // two ways around, but nothing to cut through
+        let race = Race::new("#######\n#S...E#\n#.###.#\n#.....#\n#######");
+
+        assert_eq!(race.cheats(2, 1), vec![]);
+
+        // a dead end by the start can cut through to the finish too
+        let race = Race::new("#########\n#S......#\n#.#####.#\n#.#E....#\n#########");
+        let cheats = race.cheats(2, 1);
+
+        assert_eq!(histogram(&cheats), BTreeMap::from([(2, 1), (4, 1), (6, 1), (8, 2)]));
+        assert!(cheats.contains(&Cheat { start: (3, 1), end: (3, 3), saving: 8 }));
+    }
 }
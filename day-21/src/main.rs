@@ -1,259 +1,334 @@
-use std::{ cmp::Ordering, collections::HashMap, fs, iter::repeat, time::Instant };
-use lib::{ get_part, Grid };
+use std::{ collections::{ HashMap, VecDeque }, fs, iter::repeat_n, time::Instant };
+use lib::{ flag_value, get_part, has_flag };
+
+const NUMERIC: &str = "789\n456\n123\n 0A";
+const DIRECTIONAL: &str = " ^A\n<v>";
+
+/** how many of your presses it takes to move from one key to another and press it */
+type Costs = HashMap<(char, char), u128>;
 
-// using char just for debugging
 struct Keypad {
-    current: char,
-    map: HashMap<(char, char), Vec<char>>,
+    keys: HashMap<char, (isize, isize)>,
+    // every position that has a key; anything else is a gap
+    cells: HashMap<(isize, isize), char>,
+    // candidate paths between every pair of keys (including a key and itself)
+    map: HashMap<(char, char), Vec<Vec<char>>>,
 }
 
-impl Clone for Keypad {
-    fn clone(&self) -> Self {
-        Self {
-            // today I realized I have no clue what clone or copy do
-            map: self.map.clone(),
-            ..*self
-        }
+impl Keypad {
+    /** rows of keys; a space (or a short row) is a gap the arm can't hover over */
+    fn from_layout(layout: &str) -> Self {
+        let cells = layout
+            .lines()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, key)| *key != ' ')
+                    .map(move |(c, key)| ((r as isize, c as isize), key))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let keys = cells
+            .iter()
+            .map(|(&pos, &key)| (key, pos))
+            .collect();
+
+        let mut keypad = Self { keys, cells, map: HashMap::new() };
+
+        keypad.map = get_map_of_keys(&keypad);
+
+        keypad
     }
-}
 
-// half of the problem was getting the initial paths right
-fn get_map_of_keys(keys: &str, empty: (usize, usize)) -> HashMap<(char, char), Vec<char>> {
-    let grid = Grid::new_with_chars(keys);
-    let mut map = HashMap::new();
+    fn new_directional() -> Self {
+        Self::from_layout(DIRECTIONAL)
+    }
 
-    // utility closure for repeating chars
-    let get_path = |a: char, n: usize, b: char, x: usize| -> Vec<char> {
-        repeat(a).take(n).chain(repeat(b).take(x)).collect::<Vec<char>>()
-    };
+    fn new_numeric() -> Self {
+        Self::from_layout(NUMERIC)
+    }
 
-    // get all paths to and from all keys
-    for (r, c, &cell) in grid.iter() {
-        // ignore empty
-        if (r, c) == empty {
-            continue;
-        }
-        // iterate every other cell
-        for (y, x, &other) in grid.iter() {
-            if (y, x) == empty || cell == other {
-                continue;
-            }
+    /** walks `path` from `from`, checking it never leaves the keys */
+    fn is_valid(&self, from: (isize, isize), path: &[char]) -> bool {
+        let mut pos = from;
 
-            let ud = r.abs_diff(y);
-            let lr = c.abs_diff(x);
-
-            // prefer left-up, left-down, down-right, up-right
-            // avoid empty
-            // group similar directions together
-            let mut path = match (r.cmp(&y), c.cmp(&x)) {
-                // left-up
-                (Ordering::Greater, Ordering::Greater) => get_path('<', lr, '^', ud),
-                // left-down
-                (Ordering::Less, Ordering::Greater) => get_path('<', lr, 'v', ud),
-                // down-right
-                (Ordering::Less, Ordering::Less) => get_path('v', ud, '>', lr),
-                // up-right
-                (Ordering::Greater, Ordering::Less) => get_path('^', ud, '>', lr),
-                (Ordering::Equal, Ordering::Less) => repeat('>').take(lr).collect(),
-                (Ordering::Equal, Ordering::Greater) => repeat('<').take(lr).collect(),
-                (Ordering::Less, Ordering::Equal) => repeat('v').take(ud).collect(),
-                (Ordering::Greater, Ordering::Equal) => repeat('^').take(ud).collect(),
-                _ => panic!("equal, equal!? {r} {c} {y} {x} {cell} {other}"),
-            };
+        path.iter().all(|&dir| {
+            pos = step(pos, dir);
+            self.cells.contains_key(&pos)
+        })
+    }
 
-            // reverse any that go over the empty
-            if (r == empty.0 || c == empty.1) && (y == empty.0 || x == empty.1) {
-                path = path.into_iter().rev().collect::<Vec<_>>();
+    /**
+     * every shortest path that avoids the gaps, even ones that double back;
+     * only needed when both L-shapes are blocked
+     */
+    fn shortest_paths(&self, from: (isize, isize), to: (isize, isize)) -> Vec<Vec<char>> {
+        // bfs distances back from `to`
+        let mut dist = HashMap::from([(to, 0)]);
+        let mut queue = VecDeque::from([to]);
+
+        while let Some(pos) = queue.pop_front() {
+            for dir in ['^', 'v', '<', '>'] {
+                let next = step(pos, dir);
+
+                if self.cells.contains_key(&next) && !dist.contains_key(&next) {
+                    dist.insert(next, dist[&pos] + 1);
+                    queue.push_back(next);
+                }
             }
-
-            map.insert((cell, other), path);
         }
+
+        self.walk_down(from, &dist)
     }
 
-    // print out a beautifully sorted list of paths
-    // let mut sorted = map.keys().collect::<Vec<_>>();
+    /** every path that goes one step closer each time */
+    fn walk_down(
+        &self,
+        from: (isize, isize),
+        dist: &HashMap<(isize, isize), usize>
+    ) -> Vec<Vec<char>> {
+        let Some(&d) = dist.get(&from) else {
+            return vec![];
+        };
+
+        if d == 0 {
+            return vec![vec![]];
+        }
 
-    // sorted.sort();
+        let mut out = vec![];
 
-    // for key in sorted {
-    //     println!("{key:?} {:?}", map.get(key).unwrap());
-    // }
+        for dir in ['^', 'v', '<', '>'] {
+            let next = step(from, dir);
 
-    map
-}
-
-impl Keypad {
-    fn new_directional() -> Self {
-        Self {
-            map: get_map_of_keys(" ^A\n<v>", (0, 0)),
-            current: 'A',
+            if dist.get(&next) != Some(&(d - 1)) {
+                continue;
+            }
+            for mut path in self.walk_down(next, dist) {
+                path.insert(0, dir);
+                out.push(path);
+            }
         }
-    }
 
-    fn new_numeric() -> Self {
-        Self {
-            map: get_map_of_keys("789\n456\n123\n 0A", (3, 0)),
-            current: 'A',
-        }
+        out
     }
+}
 
-    fn move_to(&mut self, key: char) -> Option<&Vec<char>> {
-        if self.current == key {
-            // we're already here; now what?
-            return None;
-        }
-        let path = self.map
-            .get(&(self.current, key))
-            .unwrap_or_else(|| { panic!("what is this? {key}") });
+fn step(pos: (isize, isize), dir: char) -> (isize, isize) {
+    match dir {
+        '^' => (pos.0 - 1, pos.1),
+        'v' => (pos.0 + 1, pos.1),
+        '<' => (pos.0, pos.1 - 1),
+        '>' => (pos.0, pos.1 + 1),
+        _ => panic!("what direction is this? {dir}"),
+    }
+}
 
-        // update current
-        self.current = key;
+// half of the problem was getting the initial paths right
+// (now we just keep both orderings and let the costs decide)
+fn get_map_of_keys(keypad: &Keypad) -> HashMap<(char, char), Vec<Vec<char>>> {
+    let mut map = HashMap::new();
 
-        Some(path)
-    }
+    // get all paths to and from all keys
+    for (&cell, &from) in keypad.keys.iter() {
+        for (&other, &to) in keypad.keys.iter() {
+            let ud = from.0.abs_diff(to.0);
+            let lr = from.1.abs_diff(to.1);
+            let vertical = if to.0 > from.0 { 'v' } else { '^' };
+            let horizontal = if to.1 > from.1 { '>' } else { '<' };
+
+            // horizontal first, then vertical first
+            let mut paths: Vec<Vec<char>> = vec![
+                repeat_n(horizontal, lr).chain(repeat_n(vertical, ud)).collect(),
+                repeat_n(vertical, ud).chain(repeat_n(horizontal, lr)).collect()
+            ];
+
+            // straight lines are the same both ways
+            paths.dedup();
+            paths.retain(|path| keypad.is_valid(from, path));
+
+            if paths.is_empty() {
+                paths = keypad.shortest_paths(from, to);
+            }
 
-    /**
-     * Move from A, to a bunch of places, then press A
-     * this is chunked into separate paths for memoization
-     */
-    fn move_sequence(&self, keys: &Vec<char>) -> Vec<Vec<char>> {
-        let mut cur = 'A';
-        let mut path = vec![];
-
-        for &next in keys {
-            let mut inner = vec![];
-            if let Some(p) = self.map.get(&(cur, next)) {
-                inner.extend(p);
-                cur = next;
+            if paths.is_empty() {
+                panic!("can't get from {cell} to {other} without hovering over a gap");
             }
-            inner.push('A');
 
-            path.push(inner);
+            map.insert((cell, other), paths);
         }
-
-        path
     }
+
+    map
+}
+
+/** cost of pressing `seq` in order, starting from 'A' */
+fn sequence_cost(seq: impl IntoIterator<Item = char>, costs: &Costs) -> Option<u128> {
+    let mut cur = 'A';
+
+    seq.into_iter().try_fold(0u128, |acc, next| {
+        let cost = costs[&(cur, next)];
+        cur = next;
+        acc.checked_add(cost)
+    })
 }
 
-/* In summary, there are the following keypads:
+/**
+ * costs for `pad`, which is driven by a robot on a directional pad with `above` costs:
+ * whichever candidate path is cheapest up there wins, plus pressing 'A'
+ */
+fn costs_through(pad: &Keypad, above: &Costs) -> Option<Costs> {
+    pad.map
+        .iter()
+        .map(|(&pair, paths)| {
+            let best = paths
+                .iter()
+                .map(|path| sequence_cost(path.iter().copied().chain(['A']), above))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .min()?;
+
+            Some((pair, best))
+        })
+        .collect()
+}
 
-    One directional keypad that you are using.
-    Two directional keypads that robots are using.
-    One numeric keypad (on a door) that a robot is using.
-*/
-// TODO: I didn't need any of this
-fn get_keypads(num: usize) -> Vec<Keypad> {
-    let direction = Keypad::new_directional();
+/** the door, driven by a chain of `robots` robots on directional pads, driven by you */
+fn door_costs(door: &Keypad, robot: &Keypad, robots: usize) -> Option<Costs> {
+    let levels = robot_costs(robot, robots)?;
 
-    let mut keypads = vec![Keypad::new_numeric(), direction.clone()];
+    costs_through(door, levels.last().unwrap())
+}
 
-    for _ in 0..num {
-        keypads.push(direction.clone());
+/** costs for each directional pad in the chain; the first is yours */
+fn robot_costs(robot: &Keypad, robots: usize) -> Option<Vec<Costs>> {
+    // you press each of your keys exactly once
+    let mut levels: Vec<Costs> = vec![
+        robot.map
+            .keys()
+            .map(|&pair| (pair, 1))
+            .collect()
+    ];
+
+    for i in 0..robots {
+        levels.push(costs_through(robot, &levels[i])?);
     }
 
-    keypads
+    Some(levels)
 }
 
-fn part_one(data: &str) -> usize {
-    let mut keypads = get_keypads(2);
-    let keypad_count = keypads.len();
-    let mut complexities = 0;
+/** the cheapest way to make `pad` press `seq`, according to the pad `above` it */
+fn expand(seq: &[char], pad: &Keypad, above: &Costs) -> Vec<char> {
+    let mut cur = 'A';
+    let mut out = vec![];
 
-    for code in data.lines() {
-        // get numeric from code
-        let num = code
-            .chars()
-            .take_while(|x| x.is_numeric())
-            .collect::<String>()
-            .parse::<usize>()
-            .expect("I thought code was a number");
+    for &next in seq {
+        let path = pad.map[&(cur, next)]
+            .iter()
+            .min_by_key(|path| sequence_cost(path.iter().copied().chain(['A']), above))
+            .unwrap();
 
-        let mut len = 0;
+        out.extend(path);
+        out.push('A');
+        cur = next;
+    }
 
-        for key in code.chars() {
-            // starts at 1
-            let mut path = vec![key];
-            for (i, pad) in keypads.iter_mut().enumerate() {
-                if i == keypad_count - 1 {
-                    // count the steps you take; not the robots
-                    len += path.len();
-                }
-                // is fold better than declaring `let mut next_path`?
-                // path gets updated for next keypad
-                path = path.iter().fold(vec![], |mut acc, &p| {
-                    // is there a better way to update and return Vec<char>?
-                    if let Some(travelled) = pad.move_to(p) {
-                        acc.extend(travelled);
-                    }
-                    // always push A
-                    acc.push('A');
-                    acc
-                });
-            }
-        }
+    out
+}
+
+// these double in length (or so) per robot
+const MAX_SEQUENCE: u128 = 10_000_000;
 
-        // println!("{num} {len}\n");
+/**
+ * the actual buttons you press to type `code`, for shallow chains;
+ * None if it's longer than `MAX_SEQUENCE`
+ */
+fn sequence(code: &str, door: &Keypad, robot: &Keypad, robots: usize) -> Option<String> {
+    let levels = robot_costs(robot, robots)?;
+    let costs = costs_through(door, levels.last().unwrap())?;
 
-        complexities += num * len;
+    if sequence_cost(code.chars(), &costs)? > MAX_SEQUENCE {
+        return None;
     }
 
-    complexities
-}
+    let code = code.chars().collect::<Vec<_>>();
+    let mut seq = expand(&code, door, &levels[robots]);
 
-// reworked this quite a bit: keypads is not mutable! (i.e. make it so we don't re-assign `.current`)
-fn dfs(
-    key: Vec<char>,
-    i: usize,
-    keypads: &Vec<Keypad>,
-    // TIL: why this is not `mut memo`: we don't need/want to reassign `memo`
-    memo: &mut HashMap<(Vec<char>, usize), usize>
-) -> usize {
-    // check if we're done
-    if i == keypads.len() - 1 {
-        return key.len();
+    for above in levels[..robots].iter().rev() {
+        seq = expand(&seq, robot, above);
     }
 
-    // annoying clone here
-    let k = (key.clone(), i);
+    Some(seq.into_iter().collect())
+}
+
+/**
+ * replays your `presses` through every robot and returns what gets typed on the door;
+ * errors if any arm hovers over a gap
+ */
+fn simulate(presses: &str, door: &Keypad, robot: &Keypad, robots: usize) -> Result<String, String> {
+    let mut pads = vec![robot; robots];
+    pads.push(door);
+
+    // every arm starts on 'A'
+    let mut arms = pads
+        .iter()
+        .map(|pad| pad.keys[&'A'])
+        .collect::<Vec<_>>();
+    let mut typed = String::new();
+
+    for (n, key) in presses.chars().enumerate() {
+        let mut key = key;
+
+        for (i, pad) in pads.iter().enumerate() {
+            if key != 'A' {
+                // a direction moves this arm, and that's it
+                arms[i] = step(arms[i], key);
+
+                if !pad.cells.contains_key(&arms[i]) {
+                    return Err(format!("press {n} ({key}) moved arm {i} over a gap"));
+                }
+                break;
+            }
+
+            // pressing 'A' presses whatever this arm is over
+            key = pad.cells[&arms[i]];
 
-    // ? did we do this before?
-    if memo.contains_key(&k) {
-        return *memo.get(&k).unwrap();
+            if i == pads.len() - 1 {
+                typed.push(key);
+            }
+        }
     }
 
-    // ? get next
-    // refactored from Vec<char> to Vec<Vec<>>
-    // so we can take advantage of memoization
-    let next = keypads[i].move_sequence(&key);
+    Ok(typed)
+}
+
+/** None if the presses don't fit in a u128 */
+fn complexity(data: &str, door: &Keypad, robot: &Keypad, robots: usize) -> Option<u128> {
+    let costs = door_costs(door, robot, robots)?;
+
+    data.lines().try_fold(0u128, |acc, code| {
+        // chatgpt for string to numeric
+        let num = code
+            .chars()
+            .take_while(|x| x.is_numeric())
+            .collect::<String>()
+            .parse::<u128>()
+            .expect("I thought code was a number");
 
-    let len = next
-        .into_iter()
-        .map(|p| dfs(p, i + 1, keypads, memo))
-        .sum();
+        let len = sequence_cost(code.chars(), &costs)?;
 
-    // ? save to memo and return
-    memo.insert(k, len);
+        acc.checked_add(len.checked_mul(num)?)
+    })
+}
 
-    len
+fn part_one(data: &str) -> u128 {
+    part_two(data, 2)
 }
 
-fn part_two(data: &str, num: usize) -> usize {
-    let keypads = get_keypads(num);
-    let mut memo = HashMap::new();
-
-    data.lines()
-        .map(|code| {
-            // chatgpt for string to numeric
-            let num = code
-                .chars()
-                .take_while(|x| x.is_numeric())
-                .collect::<String>()
-                .parse::<usize>()
-                .expect("I thought code was a number");
-
-            dfs(code.chars().collect::<Vec<_>>(), 0, &keypads, &mut memo) * num
-        })
-        .sum()
+fn part_two(data: &str, num: usize) -> u128 {
+    complexity(data, &Keypad::new_numeric(), &Keypad::new_directional(), num).unwrap_or_else(||
+        panic!("{num} robots overflows a u128")
+    )
 }
 
 fn main() {
@@ -269,11 +344,33 @@ fn main() {
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&data, 25);
+        let ans = part_two(&data, flag_value("robots").unwrap_or(25));
         println!("Part two: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- sequence robots=2`
+    if has_flag("sequence") {
+        let robots = flag_value("robots").unwrap_or(2);
+        let door = Keypad::new_numeric();
+        let robot = Keypad::new_directional();
+
+        for code in data.lines() {
+            let Some(seq) = sequence(code, &door, &robot, robots) else {
+                println!("{code}: too long to print");
+                continue;
+            };
+
+            let check = match simulate(&seq, &door, &robot, robots) {
+                Ok(typed) if typed == code => "ok".to_string(),
+                Ok(typed) => format!("typed {typed}!"),
+                Err(e) => e,
+            };
+
+            println!("{code}: {seq} ({} presses, {check})", seq.len());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -285,10 +382,31 @@ mod tests {
     #[test]
     fn test_map() {
         let keypad = Keypad::new_numeric();
-        assert_eq!(keypad.map.get(&('7', '8')), Some(&vec!['>']));
-        assert_eq!(keypad.map.get(&('7', '9')), Some(&vec!['>', '>']));
-        assert_eq!(keypad.map.get(&('8', 'A')), Some(&vec!['v', 'v', 'v', '>']));
-        assert_eq!(keypad.map.get(&('7', 'A')), Some(&vec!['>', '>', 'v', 'v', 'v']));
+        assert_eq!(keypad.map.get(&('7', '8')), Some(&vec![vec!['>']]));
+        assert_eq!(keypad.map.get(&('7', '9')), Some(&vec![vec!['>', '>']]));
+        assert_eq!(
+            keypad.map.get(&('8', 'A')),
+            Some(&vec![vec!['>', 'v', 'v', 'v'], vec!['v', 'v', 'v', '>']])
+        );
+        // can't go down first, over the gap
+        assert_eq!(keypad.map.get(&('7', 'A')), Some(&vec![vec!['>', '>', 'v', 'v', 'v']]));
+        assert_eq!(keypad.map.get(&('A', 'A')), Some(&vec![vec![]]));
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        // gap in the middle
+        let keypad = Keypad::from_layout("abc\nd e\nfgh");
+
+        assert_eq!(
+            keypad.map.get(&('a', 'h')),
+            Some(&vec![vec!['>', '>', 'v', 'v'], vec!['v', 'v', '>', '>']])
+        );
+        // has to go around
+        assert_eq!(
+            keypad.map.get(&('b', 'g')),
+            Some(&vec![vec!['<', 'v', 'v', '>'], vec!['>', 'v', 'v', '<']])
+        );
     }
 
     #[test]
@@ -312,4 +430,59 @@ mod tests {
 
         assert_eq!(ans, 126384);
     }
+
+    #[test]
+    fn test_sequence() {
+        let door = Keypad::new_numeric();
+        let robot = Keypad::new_directional();
+
+        assert_eq!(sequence("029A", &door, &robot, 0), Some("<A^A>^^AvvvA".to_string()));
+        assert_eq!(sequence("029A", &door, &robot, 2).map(|s| s.len()), Some(68));
+        assert_eq!(sequence("029A", &door, &robot, 40), None);
+
+        for robots in 0..=4 {
+            let costs = door_costs(&door, &robot, robots).unwrap();
+
+            for code in EXAMPLE.lines() {
+                let seq = sequence(code, &door, &robot, robots).unwrap();
+
+                assert_eq!(seq.len() as u128, sequence_cost(code.chars(), &costs).unwrap());
+                assert_eq!(simulate(&seq, &door, &robot, robots), Ok(code.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_simulate_gap() {
+        let door = Keypad::new_numeric();
+        let robot = Keypad::new_directional();
+
+        assert_eq!(simulate("<A", &door, &robot, 0), Ok("0".to_string()));
+        assert_eq!(
+            simulate("<<", &door, &robot, 0),
+            Err("press 1 (<) moved arm 0 over a gap".into())
+        );
+        // your robot's arm goes from 'A' to '^' to the gap
+        assert_eq!(
+            simulate("<<A", &door, &robot, 1),
+            Err("press 1 (<) moved arm 0 over a gap".into())
+        );
+        // your robot presses '<' twice, so the door's arm goes from 'A' to '0' to the gap
+        assert_eq!(
+            simulate("v<<AA", &door, &robot, 1),
+            Err("press 4 (<) moved arm 1 over a gap".into())
+        );
+    }
+
+    #[test]
+    fn test_deep_chain() {
+        assert_eq!(part_two(EXAMPLE, 25), 154115708116294);
+
+        let numeric = Keypad::new_numeric();
+        let directional = Keypad::new_directional();
+
+        // the presses themselves outgrow a u128 somewhere past 86 robots
+        assert!(complexity(EXAMPLE, &numeric, &directional, 86).is_some());
+        assert!(complexity(EXAMPLE, &numeric, &directional, 100).is_none());
+    }
 }
@@ -1,4 +1,4 @@
-use std::{ collections::{ HashMap, HashSet, VecDeque }, fs, time::Instant };
+use std::{ collections::VecDeque, fs, thread, time::Instant };
 use lib::get_part;
 
 fn evolve(secret: usize) -> usize {
@@ -82,98 +82,105 @@ fn _prices_and_changes(secret: usize) -> [[i32; 4]; 10] {
     out
 }
 
-const TWENTY_BITS: usize = (2usize).pow(20) - 1;
+/** every four-change sequence gets a slot: changes are -9..=9, so base 19 */
+const SEQUENCES: usize = 19 * 19 * 19 * 19;
 
-fn to_seq(bin: &usize) -> [isize; 4] {
-    let mut bin = *bin;
+/** the most bananas, and the changes the monkey should wait for */
+#[derive(Debug, PartialEq)]
+struct Best {
+    sequence: [isize; 4],
+    price: usize,
+}
+
+fn to_seq(index: usize) -> [isize; 4] {
+    let mut index = index;
     let mut seq = [0, 0, 0, 0];
 
     for i in (0..4).rev() {
-        let last_five = (bin & 0b11111) as isize;
-        seq[i] = last_five - 9;
-        bin >>= 5;
+        seq[i] = ((index % 19) as isize) - 9;
+        index /= 19;
     }
 
     seq
 }
 
-fn for_each_sequence(secret: usize, mut fun: impl FnMut(usize, usize)) {
-    let mut last_four: usize = 0;
+/**
+ * adds each buyer's first price for every sequence to `totals`;
+ * `seen[seq]` holds the last buyer (`id`) to sell at that sequence,
+ * so it never needs clearing between buyers
+ */
+fn for_each_sequence(secret: usize, id: u32, seen: &mut [u32], totals: &mut [u32]) {
+    let mut last_four = 0;
     let mut secret = secret;
     let mut current_price = secret % 10;
-    let mut seen = HashSet::new();
 
-    for _ in 0..3 {
+    for i in 0..2000 {
         secret = evolve(secret);
         let next_price = secret % 10;
 
-        let diff = (next_price as i32) - (current_price as i32);
-
+        // convert -9 -> 9 to 0 -> 18, and drop the oldest change
+        last_four = (last_four * 19 + 9 + next_price - current_price) % SEQUENCES;
         current_price = next_price;
 
-        // push back
-        last_four <<= 5;
-        // push another
-        last_four |= (9 + diff) as usize;
+        if i >= 3 && seen[last_four] != id {
+            seen[last_four] = id;
+            totals[last_four] += current_price as u32;
+        }
     }
+}
 
-    for _ in 3..2000 {
-        secret = evolve(secret);
-        let next_price = secret % 10;
+/** splits the buyers across `threads`, each with its own totals, then merges them */
+fn best_sequence(secrets: &[usize], threads: usize) -> Best {
+    let chunk = secrets.len().div_ceil(threads.max(1)).max(1);
 
-        let diff = (next_price as i32) - (current_price as i32);
+    let totals = thread::scope(|scope| {
+        let handles = secrets
+            .chunks(chunk)
+            .map(|buyers| {
+                scope.spawn(move || {
+                    let mut seen = vec![0; SEQUENCES];
+                    let mut totals = vec![0; SEQUENCES];
 
-        current_price = next_price;
+                    for (id, &secret) in buyers.iter().enumerate() {
+                        // 0 means unseen
+                        for_each_sequence(secret, (id as u32) + 1, &mut seen, &mut totals);
+                    }
 
-        // push back (5 is enough to push 18)
-        last_four <<= 5;
-        // push another
-        // convert -9 -> 9 to 0 -> 18
-        last_four |= (9 + diff) as usize;
+                    totals
+                })
+            })
+            .collect::<Vec<_>>();
 
-        // keep 20 bits
-        last_four &= TWENTY_BITS;
+        let mut merged = vec![0u32; SEQUENCES];
 
-        if !seen.contains(&last_four) {
-            fun(last_four, current_price);
-            seen.insert(last_four);
+        for handle in handles {
+            for (sum, part) in merged.iter_mut().zip(handle.join().unwrap()) {
+                *sum += part;
+            }
         }
-    }
+
+        merged
+    });
+
+    // first (lowest) sequence wins ties, so the answer doesn't depend on threads
+    let (index, &price) = totals
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, &price)| price)
+        .unwrap();
+
+    Best { sequence: to_seq(index), price: price as usize }
 }
 
-fn part_two(data: &str) -> usize {
+fn part_two(data: &str) -> Best {
     let secrets = data
         .lines()
         .filter_map(|x| x.parse::<usize>().ok())
         .collect::<Vec<_>>();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
 
-    let mut map = HashMap::new();
-
-    for secret in secrets {
-        // get every sequence for every secret
-        for_each_sequence(secret, |seq, price| {
-            map.entry(seq)
-                .and_modify(|x| {
-                    *x += price;
-                })
-                .or_insert(price);
-        });
-    }
-
-    // get best sequence:
-    let mut best = 0;
-    let mut best_seq = 0;
-
-    for (&seq, &sum) in map.iter() {
-        if sum > best {
-            best = sum;
-            best_seq = seq;
-        }
-    }
-
-    println!("{:?}", to_seq(&best_seq));
-
-    best
+    best_sequence(&secrets, threads)
 }
 
 fn main() {
@@ -229,6 +236,23 @@ mod tests {
 3
 2024");
 
-        assert_eq!(ans, 23);
+        assert_eq!(ans, Best { sequence: [-2, 1, -1, 3], price: 23 });
+    }
+
+    #[test]
+    fn test_threads() {
+        let secrets = [1, 2, 3, 2024, 123, 10, 100];
+        let single = best_sequence(&secrets, 1);
+
+        for threads in 2..=8 {
+            assert_eq!(best_sequence(&secrets, threads), single);
+        }
+    }
+
+    #[test]
+    fn test_to_seq() {
+        assert_eq!(to_seq(0), [-9, -9, -9, -9]);
+        assert_eq!(to_seq(SEQUENCES - 1), [9, 9, 9, 9]);
+        assert_eq!(to_seq(((7 * 19 + 10) * 19 + 8) * 19 + 12), [-2, 1, -1, 3]);
     }
 }
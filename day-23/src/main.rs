@@ -1,144 +1,327 @@
-use std::{ collections::{ HashMap, HashSet }, fs, time::Instant };
-use lib::get_part;
+use std::{ fs, time::Instant };
+use lib::{ flag_value, get_part, graph::Graph, has_flag };
 
-fn get_networks(data: &str) -> HashMap<&str, HashSet<&str>> {
-    let mut map: HashMap<&str, HashSet<&str>> = HashMap::new();
+/** fixed-size set of node ids, one bit each */
+#[derive(Clone, Debug, PartialEq)]
+struct Bitset(Vec<u64>);
 
-    for line in data.lines() {
-        let (a, b) = line.split_once("-").unwrap();
+impl Bitset {
+    fn new(size: usize) -> Self {
+        Self(vec![0; size.div_ceil(64)])
+    }
 
-        map.entry(a)
-            .and_modify(|x| {
-                x.insert(b);
-            })
-            .or_insert_with(|| { HashSet::from([b]) });
+    fn full(size: usize) -> Self {
+        let mut set = Self::new(size);
+
+        for id in 0..size {
+            set.insert(id);
+        }
+
+        set
+    }
+
+    fn insert(&mut self, id: usize) {
+        self.0[id / 64] |= 1 << (id % 64);
+    }
+
+    fn remove(&mut self, id: usize) {
+        self.0[id / 64] &= !(1 << (id % 64));
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        self.0[id / 64] & (1 << (id % 64)) != 0
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .zip(&other.0)
+                .map(|(a, b)| a & b)
+                .collect()
+        )
+    }
+
+    fn or(&self, other: &Self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .zip(&other.0)
+                .map(|(a, b)| a | b)
+                .collect()
+        )
+    }
+
+    fn len(&self) -> usize {
+        self.0
+            .iter()
+            .map(|x| x.count_ones() as usize)
+            .sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&x| x == 0)
+    }
 
-        map.entry(b)
-            .and_modify(|x| {
-                x.insert(a);
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &word)| {
+                let mut word = word;
+
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    // clear lowest bit
+                    word &= word - 1;
+
+                    Some(i * 64 + bit)
+                })
             })
-            .or_insert_with(|| { HashSet::from([a]) });
     }
+}
 
-    map
+/** computers by id, with a bitset of connections for each */
+struct Network {
+    graph: Graph,
+    adjacency: Vec<Bitset>,
 }
 
-fn part_one(networks: &HashMap<&str, HashSet<&str>>) -> usize {
-    let mut trios = HashSet::new();
+impl Network {
+    fn new(data: &str) -> Self {
+        let mut graph = Graph::undirected();
 
-    for (a, a_set) in networks.iter() {
-        // println!("{a} {a_set:?}");
+        for line in data.lines() {
+            let (a, b) = line.split_once("-").unwrap();
 
-        for b in a_set.iter() {
-            for c in networks[b].iter() {
-                if a_set.contains(c) {
-                    let mut nets = [a, b, c];
-                    nets.sort();
+            graph.connect(a, b);
+        }
 
-                    trios.insert(nets);
+        let adjacency = graph
+            .nodes()
+            .map(|id| {
+                let mut set = Bitset::new(graph.len());
+
+                for &next in graph.neighbours(id) {
+                    set.insert(next as usize);
                 }
-            }
-        }
+
+                set
+            })
+            .collect();
+
+        Self { graph, adjacency }
     }
 
-    trios
-        .iter()
-        .filter(|x| {
-            for a in x.iter() {
-                if a.starts_with('t') {
-                    return true;
-                }
+    /** sorted names, joined like the password */
+    fn password(&self, clique: &[usize]) -> String {
+        let mut names = clique
+            .iter()
+            .map(|&id| self.graph.name(id as u32))
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names.join(",")
+    }
+
+    /**
+     * bron–kerbosch with pivoting: `clique` can grow by anything in `candidates`,
+     * and anything in `excluded` was already tried; `visit` gets every maximal clique
+     * and returns how big the next one has to be to be worth looking for (`wanted`)
+     */
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        candidates: Bitset,
+        excluded: Bitset,
+        wanted: &mut usize,
+        visit: &mut impl FnMut(&[usize]) -> usize
+    ) {
+        if candidates.is_empty() {
+            if excluded.is_empty() {
+                *wanted = visit(clique);
             }
+            return;
+        }
 
-            false
-        })
-        .count()
-}
+        // prune when even taking every candidate can't get big enough
+        if clique.len() + candidates.len() < *wanted {
+            return;
+        }
 
-fn part_two(networks: &HashMap<&str, HashSet<&str>>) -> String {
-    let mut biggest = HashMap::new();
+        // the pivot with the most candidates as neighbours leaves the fewest to branch on
+        let pivot = candidates
+            .or(&excluded)
+            .iter()
+            .max_by_key(|&u| candidates.and(&self.adjacency[u]).len())
+            .unwrap();
 
-    for (a, a_set) in networks.iter() {
-        let mut visited = HashSet::from([a]);
-        let mut cur = HashSet::from([a]);
-        let mut queue = a_set.into_iter().collect::<Vec<_>>();
+        let mut candidates = candidates;
+        let mut excluded = excluded;
 
-        while let Some(v) = queue.pop() {
-            if visited.contains(&v) {
+        for v in candidates.clone().iter() {
+            if self.adjacency[pivot].contains(v) {
                 continue;
             }
 
-            if networks[v].contains(a) {
-                // keep going
-                cur.insert(v);
+            clique.push(v);
+            self.bron_kerbosch(
+                clique,
+                candidates.and(&self.adjacency[v]),
+                excluded.and(&self.adjacency[v]),
+                wanted,
+                visit
+            );
+            clique.pop();
+
+            candidates.remove(v);
+            excluded.insert(v);
+        }
+    }
 
-                for b in networks[v].iter() {
-                    queue.push(b);
-                }
-            }
+    /** every clique that can't be made any bigger */
+    fn maximal_cliques(&self) -> Vec<Vec<usize>> {
+        let mut cliques = vec![];
+        let size = self.graph.len();
 
-            visited.insert(v);
-        }
+        let (all, none) = (Bitset::full(size), Bitset::new(size));
 
-        biggest.insert(a, cur);
+        self.bron_kerbosch(&mut vec![], all, none, &mut 0, &mut |clique| {
+            cliques.push(clique.to_vec());
+            0
+        });
+
+        cliques
     }
 
-    let mut counts = HashMap::new();
+    /** the biggest clique of all */
+    fn maximum_clique(&self) -> Vec<usize> {
+        let mut best: Vec<usize> = vec![];
+        let size = self.graph.len();
 
-    // check for intersections in all queues
-    for (a, set) in biggest.iter() {
-        for b in set {
-            if a == b {
-                continue;
+        let (all, none) = (Bitset::full(size), Bitset::new(size));
+
+        self.bron_kerbosch(&mut vec![], all, none, &mut 0, &mut |clique| {
+            if clique.len() > best.len() {
+                best = clique.to_vec();
             }
-            let other = &biggest[b];
-            let mut intersection = set.intersection(other).collect::<Vec<_>>();
+            // only bother with anything bigger
+            best.len() + 1
+        });
 
-            intersection.sort();
+        best
+    }
 
-            counts
-                .entry(intersection)
-                .and_modify(|x| {
-                    *x += 1;
-                })
-                .or_insert(1);
-        }
+    /** every clique of exactly `k` computers, each listed once (ids ascending) */
+    fn cliques(&self, k: usize) -> Vec<Vec<usize>> {
+        let mut found = vec![];
+
+        self.extend(&mut vec![], Bitset::full(self.graph.len()), k, &mut found);
+
+        found
     }
 
-    let mut biggest = (0, vec![]);
+    fn extend(
+        &self,
+        clique: &mut Vec<usize>,
+        candidates: Bitset,
+        k: usize,
+        found: &mut Vec<Vec<usize>>
+    ) {
+        if clique.len() == k {
+            found.push(clique.clone());
+            return;
+        }
+
+        for v in candidates.iter() {
+            // only higher ids, so each clique comes up once
+            let mut next = candidates.and(&self.adjacency[v]);
+
+            for lower in 0..=v {
+                next.remove(lower);
+            }
 
-    for (k, v) in counts {
-        if v > biggest.0 {
-            biggest = (v, k);
+            clique.push(v);
+            self.extend(clique, next, k, found);
+            clique.pop();
         }
     }
+}
+
+fn part_one(network: &Network) -> usize {
+    network
+        .cliques(3)
+        .iter()
+        .filter(|trio| trio.iter().any(|&id| network.graph.name(id as u32).starts_with('t')))
+        .count()
+}
 
-    biggest.1
-        .into_iter()
-        .map(|x| x.to_string())
-        .collect::<Vec<_>>()
-        .join(",")
+fn part_two(network: &Network) -> String {
+    network.password(&network.maximum_clique())
 }
 
 fn main() {
     let (one, two) = get_part();
     let start = Instant::now();
     let data = fs::read_to_string("./src/input.txt").unwrap();
-    let networks = get_networks(&data);
+    let network = Network::new(&data);
 
     if one {
         let now = Instant::now();
-        let ans = part_one(&networks);
+        let ans = part_one(&network);
         println!("Part one: {:?} {:?}", ans, now.elapsed());
     }
 
     if two {
         let now = Instant::now();
-        let ans = part_two(&networks);
+        let ans = part_two(&network);
         println!("Part two: {} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    println!("Time: {:?}", start.elapsed());
+
+    // e.g. `cargo run -- cliques=4` or `cargo run -- maximal`
+    if let Some(k) = flag_value("cliques") {
+        for clique in network.cliques(k) {
+            println!("{}", network.password(&clique));
+        }
+    }
+
+    // the biggest lan party and everything connected to it;
+    // render with `dot -Tsvg src/lan.dot -o lan.svg`
+    if has_flag("dot") {
+        let mut ids = network
+            .maximum_clique()
+            .iter()
+            .map(|&id| id as u32)
+            .collect::<Vec<_>>();
+        let party = ids.len() as u32;
+
+        for id in ids.clone() {
+            for &next in network.graph.neighbours(id) {
+                if !ids.contains(&next) {
+                    ids.push(next);
+                }
+            }
+        }
+
+        // induced ids start with the party
+        let dot = network.graph
+            .induced(&ids)
+            .to_dot("lan", |id| (id < party).then(|| "style=filled, fillcolor=gold".into()));
+
+        fs::write("./src/lan.dot", dot).unwrap();
+        println!("Wrote ./src/lan.dot");
+    }
+
+    if has_flag("maximal") {
+        for clique in network.maximal_cliques() {
+            println!("{}", network.password(&clique));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -149,15 +332,47 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let ans = part_one(&get_networks(EXAMPLE));
+        let ans = part_one(&Network::new(EXAMPLE));
 
         assert_eq!(ans, 7);
     }
 
     #[test]
     fn test_part_two() {
-        let ans = part_two(&get_networks(EXAMPLE));
+        let ans = part_two(&Network::new(EXAMPLE));
 
         assert_eq!(ans, "co,de,ka,ta");
     }
+
+    #[test]
+    fn test_cliques() {
+        let network = Network::new(EXAMPLE);
+        let mut trios = network
+            .cliques(3)
+            .iter()
+            .map(|x| network.password(x))
+            .collect::<Vec<_>>();
+
+        trios.sort();
+
+        assert_eq!(trios.len(), 12);
+        assert_eq!(&trios[..3], ["aq,cg,yn", "aq,vc,wq", "co,de,ka"]);
+        assert_eq!(network.cliques(4).len(), 1);
+        assert_eq!(network.cliques(5).len(), 0);
+    }
+
+    #[test]
+    fn test_maximal_cliques() {
+        // a square with one diagonal, and a loose pair
+        let network = Network::new("a-b\nb-c\nc-d\nd-a\na-c\nx-y");
+        let mut cliques = network
+            .maximal_cliques()
+            .iter()
+            .map(|x| network.password(x))
+            .collect::<Vec<_>>();
+
+        cliques.sort();
+
+        assert_eq!(cliques, ["a,b,c", "a,c,d", "x,y"]);
+    }
 }
@@ -1,147 +1,746 @@
-use std::{ collections::{ HashMap, HashSet }, fs, time::Instant };
-use lib::get_part;
+use std::{ collections::{ HashSet, VecDeque }, fmt::Display, fs, time::Instant };
+use lib::{ flag_value, get_part, graph::Graph, has_flag };
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Op {
-    AND,
-    XOR,
-    OR,
+    And,
+    Xor,
+    Or,
 }
 
-#[derive(Debug)]
-struct System<'a> {
-    wires: HashMap<&'a str, u8>,
-    gates: HashMap<&'a str, (&'a str, &'a str, Op)>,
-    zeds: isize,
+impl Op {
+    fn color(&self) -> &'static str {
+        match self {
+            Op::And => "lightblue",
+            Op::Xor => "gold",
+            Op::Or => "palegreen",
+        }
+    }
 }
 
-// TODO: figure out the difference between impl<'a>, System<'_>, and fn new<'a>
-impl<'a> System<'a> {
-    fn new(data: &'a str) -> Self {
-        let mut wires: HashMap<&str, u8> = HashMap::new();
-        let mut gates: HashMap<&str, (&str, &str, Op)> = HashMap::new();
-        let mut zeds = 0;
+/** an `x##`/`y##` wire set at the top of the file */
+#[derive(Debug, Clone, Copy)]
+struct Input {
+    wire: u32,
+    /** `x` or `y` */
+    side: char,
+    bit: u32,
+    value: u8,
+}
+
+#[derive(Debug, Clone)]
+struct System {
+    /** every wire; edges go from a gate's inputs to its output */
+    graph: Graph,
+    inputs: Vec<Input>,
+    /** indexed by output wire */
+    gates: Vec<Option<(u32, u32, Op)>>,
+    /** `z##` wires, z00 first */
+    zeds: Vec<u32>,
+}
+
+impl System {
+    fn new(data: &str) -> Self {
+        let mut graph = Graph::directed();
+        let mut inputs = vec![];
+        let mut defined = vec![];
 
         let (w, g) = data.split_once("\n\n").unwrap();
 
         for w in w.lines() {
             let (name, number) = w.split_once(": ").unwrap();
 
-            wires.insert(name, if number == "1" { 1 } else { 0 });
+            inputs.push(Input {
+                wire: graph.intern(name),
+                side: name.chars().next().unwrap(),
+                bit: name[1..].parse().unwrap(),
+                value: if number == "1" { 1 } else { 0 },
+            });
         }
 
         for g in g.lines() {
             let arr = g.split(" ").collect::<Vec<_>>();
-            let wire = arr[4];
-
-            if wire.starts_with("z") {
-                zeds += 1;
-            }
+            let (a, _) = graph.connect(arr[0], arr[4]);
+            let (b, wire) = graph.connect(arr[2], arr[4]);
 
-            gates.insert(wire, (
-                arr[0],
-                arr[2],
+            defined.push((wire, (
+                a,
+                b,
                 match arr[1] {
-                    "AND" => Op::AND,
-                    "XOR" => Op::XOR,
-                    "OR" => Op::OR,
+                    "AND" => Op::And,
+                    "XOR" => Op::Xor,
+                    "OR" => Op::Or,
                     _ => panic!("What is this?! {}", arr[1]),
                 },
-            ));
+            )));
+        }
+
+        let mut gates = vec![None; graph.len()];
+
+        for (wire, gate) in defined {
+            gates[wire as usize] = Some(gate);
         }
 
+        let mut zeds = graph
+            .nodes()
+            .filter(|&w| graph.name(w).starts_with("z"))
+            .collect::<Vec<_>>();
+
+        zeds.sort_by_key(|&w| graph.name(w)[1..].parse::<usize>().unwrap());
+
         Self {
-            wires,
+            graph,
+            inputs,
             gates,
             zeds,
         }
     }
-}
 
-fn part_one(system: &mut System) -> usize {
-    let mut visited: HashSet<&str> = HashSet::new();
-    let gates = &system.gates;
-    let wires = &mut system.wires;
+    fn name(&self, wire: u32) -> &str {
+        self.graph.name(wire)
+    }
+
+    fn gate(&self, wire: u32) -> Option<(u32, u32, Op)> {
+        self.gates[wire as usize]
+    }
+
+    /** every gate's output wire */
+    fn outputs(&self) -> impl Iterator<Item = u32> + '_ {
+        self.graph.nodes().filter(|&w| self.gate(w).is_some())
+    }
+
+    /**
+     * Kahn's algorithm: every gate comes after the gates that feed it.
+     * An input is "defined" if it's an initial wire or another gate's output
+     */
+    fn order(&self) -> Result<Vec<u32>, EvalError> {
+        let mut defined = vec![false; self.graph.len()];
+
+        for input in self.inputs.iter() {
+            defined[input.wire as usize] = true;
+        }
+
+        let mut indegree = vec![0; self.graph.len()];
+
+        for wire in self.outputs() {
+            let (a, b, _) = self.gate(wire).unwrap();
+
+            for input in [a, b] {
+                if !defined[input as usize] && self.gate(input).is_none() {
+                    return Err(EvalError::Undefined {
+                        wire: self.name(input).to_string(),
+                        gate: self.name(wire).to_string(),
+                    });
+                }
+            }
+
+            // edges are unique, so `a AND a` only counts once
+            for &next in self.graph.neighbours(wire) {
+                indegree[next as usize] += 1;
+            }
+        }
+
+        let mut queue: VecDeque<u32> = self
+            .outputs()
+            .filter(|&w| indegree[w as usize] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.graph.len());
+
+        while let Some(wire) = queue.pop_front() {
+            order.push(wire);
+
+            for &next in self.graph.neighbours(wire) {
+                let deg = &mut indegree[next as usize];
+                *deg -= 1;
+                if *deg == 0 {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        if order.len() < self.outputs().count() {
+            return Err(EvalError::Cycle(self.find_cycle(&indegree)));
+        }
+
+        Ok(order)
+    }
+
+    /** anything left with an indegree is on (or behind) a cycle; walk back until we repeat */
+    fn find_cycle(&self, indegree: &[usize]) -> Vec<String> {
+        let remaining = |w: u32| indegree[w as usize] > 0;
+
+        let mut wire = self
+            .outputs()
+            .filter(|&w| remaining(w))
+            .min_by_key(|&w| self.name(w))
+            .unwrap();
+        let mut path: Vec<u32> = vec![];
+
+        loop {
+            if let Some(i) = path.iter().position(|&w| w == wire) {
+                return path[i..]
+                    .iter()
+                    .rev()
+                    .map(|&w| self.name(w).to_string())
+                    .collect();
+            }
+            path.push(wire);
+
+            let (a, b, _) = self.gate(wire).unwrap();
+            // at least one input must still be waiting
+            wire = if remaining(a) { a } else { b };
+        }
+    }
+
+    /** runs every gate in `order` against `wires` */
+    fn run(&self, order: &[u32], wires: &mut [u8]) {
+        for &wire in order {
+            let (a, b, op) = self.gate(wire).unwrap();
+            let a = wires[a as usize];
+            let b = wires[b as usize];
+
+            wires[wire as usize] = match op {
+                Op::And => a & b,
+                Op::Xor => a ^ b,
+                Op::Or => a | b,
+            };
+        }
+    }
+
+    /** evaluates the network using the wires from the input file */
+    fn evaluate(&self) -> Result<usize, EvalError> {
+        let order = self.order()?;
+        let mut wires = vec![0; self.graph.len()];
+
+        for input in self.inputs.iter() {
+            wires[input.wire as usize] = input.value;
+        }
+
+        self.run(&order, &mut wires);
+
+        Ok(self.output(&wires))
+    }
+
+    /** evaluates the network with arbitrary `x` and `y` values */
+    fn evaluate_with(&self, x: usize, y: usize) -> Result<usize, EvalError> {
+        let order = self.order()?;
 
-    while visited.len() < gates.len() {
-        for (wire, v) in gates.iter() {
-            if visited.contains(wire) {
+        Ok(self.compute(&order, x, y))
+    }
+
+    /** same as `evaluate_with`, but reuses an existing `order` */
+    fn compute(&self, order: &[u32], x: usize, y: usize) -> usize {
+        let mut wires = self.wires_for(x, y);
+
+        self.run(order, &mut wires);
+
+        self.output(&wires)
+    }
+
+    /** number of `x##` (and `y##`) input bits */
+    fn input_bits(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|input| input.side == 'x')
+            .count()
+    }
+
+    /** looks up named swaps, like `("z00", "c00")` */
+    fn swap_ids(&self, swaps: &[(&str, &str)]) -> Vec<(u32, u32)> {
+        swaps
+            .iter()
+            .map(|(a, b)| (self.graph.id(a).unwrap(), self.graph.id(b).unwrap()))
+            .collect()
+    }
+
+    /** copy of the system with each pair of gate outputs swapped */
+    fn with_swaps(&self, swaps: &[(u32, u32)]) -> Self {
+        let mut system = self.clone();
+
+        for &(a, b) in swaps {
+            let gate_a = system.gate(a).unwrap();
+            let gate_b = system.gate(b).unwrap();
+
+            for (wire, (x, y, _)) in [(a, gate_a), (b, gate_b)] {
+                system.graph.remove_edge(x, wire);
+                system.graph.remove_edge(y, wire);
+            }
+            for (wire, (x, y, _)) in [(a, gate_b), (b, gate_a)] {
+                system.graph.add_edge(x, wire);
+                system.graph.add_edge(y, wire);
+            }
+
+            system.gates.swap(a as usize, b as usize);
+        }
+
+        system
+    }
+
+    /** checks that `x + y == z`; see `verify` */
+    fn verify_adder(&self, swaps: &[(&str, &str)]) -> Result<Vec<usize>, EvalError> {
+        self.verify(&self.swap_ids(swaps), &Target::Add)
+    }
+
+    /**
+     * checks that `z == target(x, y)`, with the width taken from the `z` wires:
+     * every bit on its own (with and without a carry in) and then a bunch
+     * of random inputs. Returns the output bits that were ever wrong
+     */
+    fn verify(&self, swaps: &[(u32, u32)], target: &Target) -> Result<Vec<usize>, EvalError> {
+        let system = self.with_swaps(swaps);
+        let order = system.order()?;
+        let input_mask = (1 << system.input_bits()) - 1;
+        let mask = (1 << system.zeds.len()) - 1;
+        let mut wrong = 0;
+
+        let mut check = |x: usize, y: usize| {
+            let z = system.compute(&order, x, y);
+            wrong |= (z ^ target.apply(x, y)) & mask;
+        };
+
+        for bit in 0..system.zeds.len() {
+            for (x, y) in bit_tests(bit, input_mask) {
+                check(x, y);
+            }
+        }
+
+        let mut rng = XorShift(0x2024_1224);
+
+        for _ in 0..100 {
+            check(rng.next() & input_mask, rng.next() & input_mask);
+        }
+
+        Ok(
+            (0..system.zeds.len())
+                .filter(|bit| (wrong >> bit) & 1 == 1)
+                .collect()
+        )
+    }
+
+    /**
+     * like `verify`, but stops at the first wrong output bit, checking
+     * bits from the bottom up to `upto`
+     */
+    fn first_wrong(&self, order: &[u32], target: &Target, upto: usize) -> Option<usize> {
+        (0..=upto.min(self.zeds.len() - 1)).find_map(|bit| self.check_bit(order, target, bit))
+    }
+
+    /** runs `bit_tests` for one bit, returns the lowest wrong bit at or below it */
+    fn check_bit(&self, order: &[u32], target: &Target, bit: usize) -> Option<usize> {
+        let input_mask = (1 << self.input_bits()) - 1;
+        // only look at this bit and the ones below it
+        let mask = (1 << (bit + 1)) - 1;
+
+        bit_tests(bit, input_mask)
+            .into_iter()
+            .map(|(x, y)| (self.compute(order, x, y) ^ target.apply(x, y)) & mask)
+            .find(|&diff| diff != 0)
+            .map(|diff| diff.trailing_zeros() as usize)
+    }
+
+    /** every bit, then random inputs */
+    fn lowest_wrong(&self, order: &[u32], target: &Target) -> Option<usize> {
+        if let Some(bit) = self.first_wrong(order, target, self.zeds.len()) {
+            return Some(bit);
+        }
+
+        let input_mask = (1 << self.input_bits()) - 1;
+        let mask = (1 << self.zeds.len()) - 1;
+        let mut rng = XorShift(0x2024_1224);
+
+        (0..100)
+            .map(|_| {
+                let (x, y) = (rng.next() & input_mask, rng.next() & input_mask);
+                (self.compute(order, x, y) ^ target.apply(x, y)) & mask
+            })
+            .filter(|&diff| diff != 0)
+            .map(|diff| diff.trailing_zeros() as usize)
+            .min()
+    }
+
+    /** the gates that `wire` depends on, including itself */
+    fn cone(&self, wire: u32) -> HashSet<u32> {
+        let mut cone = HashSet::new();
+        let mut stack = vec![wire];
+
+        while let Some(wire) = stack.pop() {
+            let Some((a, b, _)) = self.gate(wire) else {
+                continue;
+            };
+            if !cone.insert(wire) {
                 continue;
             }
-            let (a, b, op) = v;
 
-            let a = wires.get(a);
-            let b = wires.get(b);
+            stack.push(a);
+            stack.push(b);
+        }
 
-            if let Some(a) = a {
-                if let Some(b) = b {
-                    wires.insert(wire, match op {
-                        Op::AND => a & b,
-                        Op::XOR => a ^ b,
-                        Op::OR => a | b,
-                    });
-                    visited.insert(wire);
+        cone
+    }
+
+    /** reads all `z##` wires as a binary number, z00 being the least significant */
+    fn output(&self, wires: &[u8]) -> usize {
+        self.zeds
+            .iter()
+            .rev()
+            .fold(0, |acc, &z| (acc << 1) | (wires[z as usize] as usize))
+    }
+
+    /** sets each `x##`/`y##` wire from the file to the matching bit of x/y */
+    fn wires_for(&self, x: usize, y: usize) -> Vec<u8> {
+        let mut wires = vec![0; self.graph.len()];
+
+        for input in self.inputs.iter() {
+            let value = match input.side {
+                'x' => x,
+                'y' => y,
+                _ => panic!("What input is this?! {}", self.name(input.wire)),
+            };
+
+            wires[input.wire as usize] = ((value >> input.bit) & 1) as u8;
+        }
+
+        wires
+    }
+
+    /** every wire, sorted by name */
+    fn sorted(&self, wires: impl Iterator<Item = u32>) -> Vec<u32> {
+        let mut wires = wires.collect::<Vec<_>>();
+
+        wires.sort_by_key(|&w| self.name(w));
+
+        wires
+    }
+
+    /**
+     * graphviz: x/y on the first rank, gates ranked by depth, z on the last;
+     * gates are filled by `Op`, and `highlight`ed gates get a thick red border
+     */
+    fn to_dot(&self, highlight: &[&str]) -> String {
+        let mut out = vec!["digraph circuit {".to_string(), "  rankdir=TB;".to_string()];
+        let names = |wires: &[u32]| {
+            wires
+                .iter()
+                .map(|&w| self.name(w))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+
+        let inputs = self.sorted(self.inputs.iter().map(|input| input.wire));
+        out.push(format!("  {{ rank=min; {}; }}", names(&inputs)));
+        out.push(format!("  {{ rank=max; {}; }}", names(&self.sorted(self.zeds.iter().copied()))));
+
+        // a swapped circuit might have a cycle; then let graphviz rank it
+        if let Ok(order) = self.order() {
+            let mut depth = vec![0; self.graph.len()];
+            let mut layers: Vec<Vec<u32>> = vec![];
+
+            for &wire in order.iter() {
+                let (a, b, _) = self.gate(wire).unwrap();
+                let d = depth[a as usize].max(depth[b as usize]) + 1;
+                depth[wire as usize] = d;
+
+                if self.name(wire).starts_with("z") {
+                    continue;
+                }
+                if layers.len() < d {
+                    layers.resize(d, vec![]);
                 }
+                layers[d - 1].push(wire);
+            }
+
+            for layer in layers.iter().filter(|l| !l.is_empty()) {
+                let layer = self.sorted(layer.iter().copied());
+
+                out.push(format!("  {{ rank=same; {}; }}", names(&layer)));
             }
         }
+
+        out.extend(
+            self.graph.dot_lines(|wire| {
+                let name = self.name(wire);
+
+                match self.gate(wire) {
+                    None => Some("shape=box".to_string()),
+                    Some((_, _, op)) => {
+                        let border = if highlight.contains(&name) {
+                            ", color=red, penwidth=3"
+                        } else {
+                            ""
+                        };
+
+                        Some(
+                            format!(
+                                "label=\"{name}\\n{op:?}\", style=filled, fillcolor={}{border}",
+                                op.color()
+                            )
+                        )
+                    }
+                }
+            })
+        );
+
+        out.push("}".to_string());
+        out.push("".to_string());
+
+        out.join("\n")
+    }
+
+    /** one line per wire: `wire -> gate (Op), ...` */
+    fn adjacency(&self) -> String {
+        self.sorted(self.graph.nodes())
+            .into_iter()
+            .map(|wire| {
+                let next = self
+                    .sorted(self.graph.neighbours(wire).iter().copied())
+                    .into_iter()
+                    .map(|g| format!("{} ({:?})", self.name(g), self.gate(g).unwrap().2))
+                    .collect::<Vec<_>>();
+
+                format!("{} -> {}", self.name(wire), next.join(", ")).trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
+}
 
-    let mut ans: usize = 0;
-    let mut zeds = system.zeds;
+#[derive(Debug, PartialEq)]
+enum EvalError {
+    /** `gate` reads a `wire` that nothing sets */
+    Undefined { wire: String, gate: String },
+    /** wires in dependency order, the last feeds back into the first */
+    Cycle(Vec<String>),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::Undefined { wire, gate } => {
+                write!(f, "gate {gate} reads undefined wire {wire}")
+            }
+            EvalError::Cycle(cycle) => {
+                write!(f, "cycle: {} -> {}", cycle.join(" -> "), cycle[0])
+            }
+        }
+    }
+}
 
-    while zeds >= 0 {
-        zeds -= 1;
+/** what the circuit is supposed to compute */
+enum Target {
+    Add,
+    And,
+    Custom(fn(usize, usize) -> usize),
+}
 
-        // leading zeroes
-        let val = format!("z{:0>2}", zeds);
+impl Target {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "add" => Target::Add,
+            "and" => Target::And,
+            "or" => Target::Custom(|x, y| x | y),
+            "xor" => Target::Custom(|x, y| x ^ y),
+            _ => panic!("What target is this?! {name}"),
+        }
+    }
 
-        if let Some(v) = wires.get(val.as_str()) {
-            ans <<= 1;
-            ans |= *v as usize;
+    fn apply(&self, x: usize, y: usize) -> usize {
+        match self {
+            Target::Add => x + y,
+            Target::And => x & y,
+            Target::Custom(f) => f(x, y),
         }
     }
+}
+
+/** x/y pairs that exercise one bit, with and without a carry in from the bit below */
+fn bit_tests(bit: usize, input_mask: usize) -> Vec<(usize, usize)> {
+    let carry = if bit > 0 { 1 << (bit - 1) } else { 0 };
 
-    ans
+    [(0, 0), (0, 1), (1, 0), (1, 1)]
+        .into_iter()
+        .flat_map(|(a, b)| [(a << bit, b << bit), ((a << bit) | carry, (b << bit) | carry)])
+        .map(|(x, y)| (x & input_mask, y & input_mask))
+        .collect()
 }
 
-fn part_two(system: &System) -> String {
+/**
+ * searches for `swaps` pairs of gate outputs that make `system` compute `target`.
+ * Fixes the lowest wrong output bit first: one side of the swap has to be in
+ * that bit's cone, and the swap can't break any of the bits below it
+ */
+fn repair(system: &System, target: &Target, swaps: usize) -> Option<String> {
+    let outputs = system.sorted(system.outputs());
+    let mut chosen = vec![];
+
+    if !search(system, target, swaps, &outputs, &mut chosen) {
+        return None;
+    }
+
+    let mut wires = chosen
+        .iter()
+        .flat_map(|&(a, b)| [system.name(a), system.name(b)])
+        .collect::<Vec<_>>();
+    wires.sort();
+
+    Some(wires.join(","))
+}
+
+fn search(
+    system: &System,
+    target: &Target,
+    remaining: usize,
+    outputs: &[u32],
+    chosen: &mut Vec<(u32, u32)>
+) -> bool {
+    let Ok(order) = system.order() else {
+        return false;
+    };
+
+    let Some(bit) = system.lowest_wrong(&order, target) else {
+        return remaining == 0;
+    };
+
+    if remaining == 0 {
+        return false;
+    }
+
+    let cone = system.cone(system.zeds[bit]);
+    let used = |w: u32| chosen.iter().any(|&(a, b)| a == w || b == w);
+
+    let mut candidates = vec![];
+
+    for &a in outputs.iter().filter(|w| cone.contains(*w)) {
+        for &b in outputs {
+            // don't try both (a, b) and (b, a)
+            if
+                a == b ||
+                (cone.contains(&b) && system.name(b) < system.name(a)) ||
+                used(a) ||
+                used(b)
+            {
+                continue;
+            }
+            candidates.push((a, b));
+        }
+    }
+
+    for swap in candidates {
+        let next = system.with_swaps(&[swap]);
+
+        let Ok(order) = next.order() else {
+            continue;
+        };
+
+        // must fix this bit without breaking any below it;
+        // checking this bit on its own first rules out most swaps quickly
+        if
+            next.check_bit(&order, target, bit).is_some() ||
+            next.first_wrong(&order, target, bit).is_some()
+        {
+            continue;
+        }
+
+        chosen.push(swap);
+
+        if search(&next, target, remaining - 1, outputs, chosen) {
+            return true;
+        }
+
+        chosen.pop();
+    }
+
+    false
+}
+
+/** tiny xorshift, so we don't need the rand crate */
+struct XorShift(usize);
+
+impl XorShift {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/** every way to split `wires` into pairs */
+fn pairings<'a>(wires: &[&'a str]) -> Vec<Vec<(&'a str, &'a str)>> {
+    let Some((&first, rest)) = wires.split_first() else {
+        return vec![vec![]];
+    };
+
+    let mut out = vec![];
+
+    for (i, &other) in rest.iter().enumerate() {
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+
+        for mut pairs in pairings(&remaining) {
+            pairs.insert(0, (first, other));
+            out.push(pairs);
+        }
+    }
+
+    out
+}
+
+/** finds a way to pair up the swapped `wires` so that the system adds */
+fn confirm<'a>(system: &System, wires: &[&'a str]) -> Option<Vec<(&'a str, &'a str)>> {
+    pairings(wires)
+        .into_iter()
+        .find(|swaps| system.verify_adder(swaps).is_ok_and(|wrong| wrong.is_empty()))
+}
+
+fn part_one(system: &System) -> usize {
+    system.evaluate().unwrap_or_else(|e| panic!("{e}"))
+}
+
+/** gates that don't fit the shape of a ripple-carry adder */
+fn suspects(system: &System) -> Vec<&str> {
     //
     // logic from maneatingape
     //
-    let mut nextgate = HashSet::new();
-
-    for (_, (left, right, op)) in system.gates.iter() {
-        nextgate.insert((left, op));
-        nextgate.insert((right, op));
-    }
+    let feeds = |wire: u32, op: Op| {
+        system.graph
+            .neighbours(wire)
+            .iter()
+            .any(|&g| system.gate(g).is_some_and(|(_, _, next)| next == op))
+    };
 
     let mut swap = vec![];
-    let x0 = &"x00";
+    let x0 = system.graph.id("x00");
+    // last carry goes straight to the last output
+    let last = system.zeds.last().copied();
+
+    for gate in system.outputs() {
+        let (left, right, op) = system.gate(gate).unwrap();
+        let first = x0 == Some(left) || x0 == Some(right);
+        let output = system.name(gate).starts_with("z");
 
-    for (gate, (left, right, op)) in system.gates.iter() {
         match op {
-            Op::AND => {
+            Op::And => {
                 // Check that all AND gates point to an OR, except for first AND.
-                if left != x0 && right != x0 && !nextgate.contains(&(gate, &Op::OR)) {
-                    swap.push(gate);
+                if !first && !feeds(gate, Op::Or) {
+                    swap.push(system.name(gate));
                 }
             }
-            Op::OR => {
+            Op::Or => {
                 // Check that only XOR gates point to output, except for last carry which is OR.
                 // OR can never point to OR.
-                if (gate.starts_with("z") && gate != &"z45") || nextgate.contains(&(gate, &Op::OR)) {
-                    swap.push(gate);
+                if (output && Some(gate) != last) || feeds(gate, Op::Or) {
+                    swap.push(system.name(gate));
                 }
             }
-            Op::XOR => {
+            Op::Xor => {
                 // Check that first level XOR points to second level XOR, except for first XOR.
-                if left.starts_with("x") || right.starts_with("x") {
-                    if left != x0 && right != x0 && !nextgate.contains(&(gate, &Op::XOR)) {
-                        swap.push(gate);
+                if system.name(left).starts_with("x") || system.name(right).starts_with("x") {
+                    if !first && !feeds(gate, Op::Xor) {
+                        swap.push(system.name(gate));
                     }
-                } else if !gate.starts_with("z") {
+                } else if !output {
                     // Second level XOR must point to output.
-                    swap.push(gate);
+                    swap.push(system.name(gate));
                 }
             }
         }
@@ -149,31 +748,68 @@ fn part_two(system: &System) -> String {
 
     swap.sort();
 
-    swap.iter()
-        .map(|x| x.to_string())
-        .collect::<Vec<_>>()
-        .join(",")
+    swap
+}
+
+fn part_two(system: &System) -> String {
+    suspects(system).join(",")
 }
 
 fn main() {
     let (one, two) = get_part();
     let start = Instant::now();
     let data = fs::read_to_string("./src/input.txt").unwrap();
-    let mut system = System::new(&data);
+    let system = System::new(&data);
 
     if one {
         let now = Instant::now();
-        let ans = part_one(&mut system);
+        let ans = part_one(&system);
         println!("Part one: {:?} {:?}", ans, now.elapsed());
+
+        // e.g. `cargo run -- one x=5 y=7`
+        if let (Some(x), Some(y)) = (flag_value("x"), flag_value("y")) {
+            match system.evaluate_with(x, y) {
+                Ok(z) => println!("{x} with {y}: {z}"),
+                Err(e) => println!("Error: {e}"),
+            }
+        }
     }
 
     if two {
         let now = Instant::now();
         let ans = part_two(&system);
         println!("Part two: {} {:?}", ans, now.elapsed());
+
+        let wires = ans.split(",").collect::<Vec<_>>();
+
+        match confirm(&system, &wires) {
+            Some(swaps) => println!("Verified: {:?}", swaps),
+            None => {
+                let wrong = system.verify_adder(&[]);
+                println!("Could not verify; unswapped wrong bits: {:?}", wrong);
+            }
+        }
+    }
+
+    println!("Time: {:?}", start.elapsed());
+
+    // render with `dot -Tsvg src/circuit.dot -o circuit.svg`
+    if has_flag("dot") {
+        fs::write("./src/circuit.dot", system.to_dot(&suspects(&system))).unwrap();
+        println!("Wrote ./src/circuit.dot");
+    }
+
+    // slow, but doesn't assume the circuit is an adder; e.g. `repair=4 target=and`
+    if let Some(swaps) = flag_value("repair") {
+        let target = Target::from_name(&flag_value::<String>("target").unwrap_or("add".into()));
+        let now = Instant::now();
+        let ans = repair(&system, &target, swaps);
+        println!("Repair: {:?} {:?}", ans, now.elapsed());
     }
 
-    println!("Time: {:?}", start.elapsed())
+    if has_flag("adjacency") {
+        println!("{}", system.adjacency());
+    }
 }
 
 #[cfg(test)]
@@ -184,8 +820,125 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let ans = part_one(&mut System::new(EXAMPLE));
+        let ans = part_one(&System::new(EXAMPLE));
 
         assert_eq!(ans, 2024);
     }
+
+    #[test]
+    fn test_evaluate_with() {
+        let system = System::new(
+            "x00: 0\nx01: 0\ny00: 0\ny01: 0\n\nx00 AND y00 -> z00\nx01 AND y01 -> z01"
+        );
+
+        assert_eq!(system.evaluate_with(0b11, 0b10), Ok(0b10));
+        assert_eq!(system.evaluate_with(0b01, 0b11), Ok(0b01));
+    }
+
+    // two bit ripple-carry adder, with z00 and the first carry swapped
+    const BROKEN_ADDER: &str = "x00: 0
+x01: 0
+y00: 0
+y01: 0
+
+x00 XOR y00 -> c00
+x00 AND y00 -> z00
+x01 XOR y01 -> s01
+x01 AND y01 -> a01
+s01 XOR c00 -> z01
+s01 AND c00 -> b01
+a01 OR b01 -> z02";
+
+    #[test]
+    fn test_verify_adder() {
+        let system = System::new(BROKEN_ADDER);
+
+        assert_eq!(system.verify_adder(&[]), Ok(vec![0, 1, 2]));
+        assert_eq!(system.verify_adder(&[("z00", "c00")]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_confirm() {
+        let system = System::new(BROKEN_ADDER);
+
+        assert_eq!(confirm(&system, &["c00", "z00"]), Some(vec![("c00", "z00")]));
+        assert_eq!(pairings(&["a", "b", "c", "d"]).len(), 3);
+    }
+
+    #[test]
+    fn test_repair() {
+        let system = System::new(
+            "x00: 0
+x01: 1
+x02: 0
+x03: 1
+x04: 0
+x05: 1
+y00: 0
+y01: 0
+y02: 1
+y03: 1
+y04: 0
+y05: 1
+
+x00 AND y00 -> z05
+x01 AND y01 -> z02
+x02 AND y02 -> z01
+x03 AND y03 -> z03
+x04 AND y04 -> z04
+x05 AND y05 -> z00"
+        );
+
+        assert_eq!(repair(&system, &Target::And, 2), Some("z00,z01,z02,z05".to_string()));
+        assert_eq!(repair(&system, &Target::And, 1), None);
+
+        let adder = System::new(BROKEN_ADDER);
+
+        assert_eq!(repair(&adder, &Target::Add, 1), Some("c00,z00".to_string()));
+        assert_eq!(
+            repair(&adder, &Target::Custom(|x, y| x + y), 1),
+            Some("c00,z00".to_string())
+        );
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let system = System::new(BROKEN_ADDER);
+        let adjacency = system.adjacency();
+
+        assert!(adjacency.contains("s01 -> b01 (And), z01 (Xor)"));
+        assert!(adjacency.ends_with("z01 ->\nz02 ->"));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let system = System::new(BROKEN_ADDER);
+        let dot = system.to_dot(&["z00"]);
+
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("{ rank=min; x00; x01; y00; y01; }"));
+        assert!(dot.contains("{ rank=max; z00; z01; z02; }"));
+        assert!(dot.contains("{ rank=same; a01; c00; s01; }"));
+        assert!(dot.contains("z00 [label=\"z00\\nAnd\", style=filled, fillcolor=lightblue, color=red, penwidth=3];"));
+        assert!(dot.contains("s01 -> z01;"));
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        let system = System::new("x00: 1\ny00: 1\n\nx00 AND q00 -> z00");
+
+        assert_eq!(
+            system.evaluate(),
+            Err(EvalError::Undefined { wire: "q00".to_string(), gate: "z00".to_string() })
+        );
+
+        let system = System::new(
+            "x00: 1\ny00: 1\n\nx00 AND aaa -> bbb\nbbb OR y00 -> ccc\nccc XOR x00 -> aaa\nccc AND y00 -> z00"
+        );
+
+        assert_eq!(
+            system.evaluate(),
+            Err(EvalError::Cycle(["bbb", "ccc", "aaa"].map(String::from).to_vec()))
+        );
+    }
 }
@@ -0,0 +1,214 @@
+use std::{ collections::HashMap, ops::Range };
+
+/**
+ * nodes are interned to dense `u32` ids, so lookups after parsing are just
+ * indexing, and nothing has to borrow from the input; `name` gets them back
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    edges: Vec<Vec<u32>>,
+    directed: bool,
+}
+
+impl Graph {
+    /** edges go one way: `a -> b` */
+    pub fn directed() -> Self {
+        Self { directed: true, ..Default::default() }
+    }
+
+    /** every edge goes both ways: `a -- b` */
+    pub fn undirected() -> Self {
+        Self::default()
+    }
+
+    /** the id for `name`, adding it if it's new */
+    pub fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.names.len() as u32;
+
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.edges.push(vec![]);
+
+        id
+    }
+
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /** every id, in the order they were interned */
+    pub fn nodes(&self) -> Range<u32> {
+        0..self.len() as u32
+    }
+
+    /** adds `a -> b` (and `b -> a` if undirected); duplicates are ignored */
+    pub fn add_edge(&mut self, a: u32, b: u32) {
+        if !self.has_edge(a, b) {
+            self.edges[a as usize].push(b);
+        }
+        if !self.directed && !self.has_edge(b, a) {
+            self.edges[b as usize].push(a);
+        }
+    }
+
+    pub fn remove_edge(&mut self, a: u32, b: u32) {
+        self.edges[a as usize].retain(|&x| x != b);
+
+        if !self.directed {
+            self.edges[b as usize].retain(|&x| x != a);
+        }
+    }
+
+    /** interns both names, then adds the edge between them */
+    pub fn connect(&mut self, a: &str, b: &str) -> (u32, u32) {
+        let (a, b) = (self.intern(a), self.intern(b));
+
+        self.add_edge(a, b);
+
+        (a, b)
+    }
+
+    pub fn has_edge(&self, a: u32, b: u32) -> bool {
+        self.edges[a as usize].contains(&b)
+    }
+
+    /** where `id`'s edges go, in the order they were added */
+    pub fn neighbours(&self, id: u32) -> &[u32] {
+        &self.edges[id as usize]
+    }
+
+    /** out-degree, if directed */
+    pub fn degree(&self, id: u32) -> usize {
+        self.edges[id as usize].len()
+    }
+
+    /** just `ids` and the edges between them; new id `i` is `ids[i]` */
+    pub fn induced(&self, ids: &[u32]) -> Graph {
+        let mut graph = Graph { directed: self.directed, ..Default::default() };
+
+        for &id in ids {
+            graph.intern(self.name(id));
+        }
+
+        for (i, &id) in ids.iter().enumerate() {
+            for &next in self.neighbours(id) {
+                if let Some(j) = ids.iter().position(|&x| x == next) {
+                    graph.add_edge(i as u32, j as u32);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /**
+     * node and edge statements for graphviz; `attributes` can style each node
+     * (e.g. `shape=box`), and nodes without any are left to their edges
+     */
+    pub fn dot_lines(&self, attributes: impl Fn(u32) -> Option<String>) -> Vec<String> {
+        let arrow = if self.directed { "->" } else { "--" };
+        let mut lines = vec![];
+
+        for id in self.nodes() {
+            if let Some(attributes) = attributes(id) {
+                lines.push(format!("  {} [{attributes}];", self.name(id)));
+            }
+        }
+
+        for a in self.nodes() {
+            for &b in self.neighbours(a) {
+                // undirected edges are stored twice, only draw them once
+                if self.directed || a < b {
+                    lines.push(format!("  {} {arrow} {};", self.name(a), self.name(b)));
+                }
+            }
+        }
+
+        lines
+    }
+
+    /** the whole graph, ready for `dot -Tsvg` */
+    pub fn to_dot(&self, title: &str, attributes: impl Fn(u32) -> Option<String>) -> String {
+        let kind = if self.directed { "digraph" } else { "graph" };
+        let mut out = vec![format!("{kind} {title} {{")];
+
+        out.extend(self.dot_lines(attributes));
+        out.push("}".to_string());
+        out.push("".to_string());
+
+        out.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut graph = Graph::undirected();
+
+        assert_eq!(graph.connect("kh", "tc"), (0, 1));
+        assert_eq!(graph.connect("tc", "qp"), (1, 2));
+        assert_eq!(graph.connect("qp", "kh"), (2, 0));
+        // again
+        graph.connect("kh", "tc");
+
+        assert_eq!(graph.id("qp"), Some(2));
+        assert_eq!(graph.id("zz"), None);
+        assert_eq!(graph.name(1), "tc");
+        assert_eq!(graph.neighbours(1), [0, 2]);
+        assert_eq!(graph.degree(0), 2);
+    }
+
+    #[test]
+    fn test_directed() {
+        let mut graph = Graph::directed();
+        let (x, z) = graph.connect("x00", "z00");
+
+        assert!(graph.has_edge(x, z));
+        assert!(!graph.has_edge(z, x));
+
+        graph.remove_edge(x, z);
+
+        assert_eq!(graph.degree(x), 0);
+    }
+
+    #[test]
+    fn test_induced_dot() {
+        let mut graph = Graph::undirected();
+
+        for (a, b) in [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")] {
+            graph.connect(a, b);
+        }
+
+        let ids = [graph.id("c").unwrap(), graph.id("d").unwrap()];
+        let sub = graph.induced(&ids);
+
+        assert_eq!(sub.len(), 2);
+        assert_eq!(sub.name(0), "c");
+        assert_eq!(sub.to_dot("lan", |_| None), "graph lan {\n  c -- d;\n}\n");
+
+        let dot = graph.to_dot("lan", |id| (id == 0).then(|| "color=red".to_string()));
+
+        assert!(dot.contains("  a [color=red];\n  a -- b;\n  a -- c;\n  b -- c;\n  c -- d;\n"));
+    }
+}
@@ -1,4 +1,6 @@
-use std::env;
+use std::{ env, str::FromStr };
+
+pub mod graph;
 
 pub fn get_part() -> (bool, bool) {
     let args = env::args().skip(1);
@@ -24,6 +26,22 @@ pub fn get_part() -> (bool, bool) {
     (hasone, hastwo)
 }
 
+/** checks for an extra cli flag, like `cargo run -- bench` */
+pub fn has_flag(flag: &str) -> bool {
+    env::args().skip(1).any(|arg| arg == flag)
+}
+
+/** parses an extra cli value, like `cargo run -- width=11` */
+pub fn flag_value<T: FromStr>(flag: &str) -> Option<T> {
+    env::args()
+        .skip(1)
+        .find_map(|arg| {
+            arg.split_once('=')
+                .filter(|(name, _)| *name == flag)
+                .and_then(|(_, value)| value.parse().ok())
+        })
+}
+
 // (r, c) differences, clockwise
 pub const DIRS: [(isize, isize); 4] = [
     (-1, 0), // top
@@ -115,8 +133,6 @@ macro_rules! tup {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_tup_add() {
         assert_eq!(tup!((1, 2) + (2, 3)), (3, 5));